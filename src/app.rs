@@ -0,0 +1,499 @@
+use crate::blackjack;
+use crate::components::{
+    chart::Chart,
+    controls::Controls,
+    count_drill::CountDrill,
+    high_scores::HighScores,
+    histogram::Histogram,
+    messages::Messages,
+    play::Play,
+    trainer::Trainer,
+    {chooser, chooser::Chooser},
+};
+use crate::leaderboard;
+use crate::simulator::{self, Simulator};
+use crate::storage::Scores;
+use stdweb::web::Date;
+use yew::{
+    agent::{Bridge, Bridged},
+    prelude::*,
+    services::{
+        fetch::{FetchService, FetchTask},
+        ConsoleService,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Welcome,
+    Choosing(chooser::State),
+    Scores,
+    HighScores,
+    Playing,
+    Training,
+    Counting,
+    None,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+pub struct Model {
+    state: State,
+    link: ComponentLink<Self>,
+    console: ConsoleService,
+    messages: Vec<String>,
+    report: Option<blackjack::Report>,
+    chooser_link: Option<ComponentLink<Chooser>>,
+    worker: Box<dyn Bridge<Simulator>>,
+    progress: Option<f64>,
+    /// The Chooser's chart the trainer checks answers against, if any
+    chart: Option<blackjack::ChartFile>,
+    /// The chart the current long run is playing, to go on the leaderboard
+    run_strategy: Option<blackjack::Strategy>,
+    fetch: FetchService,
+    submission: Option<FetchTask>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Msg {
+    ChangeState(State),
+    PushMessage(String),
+    StartGame(simulator::Request),
+    StopGame,
+    StartTraining(Option<blackjack::ChartFile>),
+    Simulator(simulator::Response),
+    Submitted(Result<(), String>),
+    AssignLink(ComponentLink<Chooser>),
+}
+
+impl Component for Model {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        // Links to a shared chart open straight into the Chooser
+        let state = match chooser::linked_chart() {
+            Some(_) => State::Choosing(chooser::State::Hard),
+            None => State::Welcome,
+        };
+        Model {
+            state,
+            link: link.clone(),
+            messages: vec![
+                "Welcome to Blackjack Online!".to_string(),
+                "It's just like normal blackjack except I've taken the fun away because you don't actually play the game!".to_string(),
+                "Instead, you just tell the computer what you would do in each situation.".to_string(),
+                "Then we run 2 million simulations and see how much you would've earnt!".to_string(),
+                "Or if you want the fun back, take a seat at the Table and play some hands yourself.".to_string(),
+            ],
+            console: ConsoleService::new(),
+            report: None,
+            chooser_link: None,
+            worker: Simulator::bridge(link.callback(Msg::Simulator)),
+            progress: None,
+            chart: None,
+            run_strategy: None,
+            fetch: FetchService::new(),
+            submission: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::ChangeState(target) => {
+                self.state = target;
+                if target != State::Scores && self.progress.take().is_some() {
+                    self.worker.send(simulator::Request::Cancel);
+                }
+                // self.console.log(&format!("{:?}", self.state)[..]);
+                match target {
+                    State::Welcome => {
+                        self.messages = vec![
+                            "Welcome to Blackjack Online!".to_string(),
+                            "It's just like normal blackjack except I've taken the fun away because you don't actually play the game!".to_string(),
+                            "Instead, you just tell the computer what you would do in each situation.".to_string(),
+                            "Then we run 2 million simulations and see how much you would've earnt!".to_string(),
+                            "Or if you want the fun back, take a seat at the Table and play some hands yourself.".to_string(),
+                        ];
+                    }
+                    _ => (),
+                }
+            }
+            Msg::PushMessage(msg) => {
+                self.messages.push(msg);
+            }
+            Msg::StartGame(request) => {
+                self.run_strategy = match &request {
+                    simulator::Request::Run(strategy, ..) => Some(strategy.clone()),
+                    _ => None,
+                };
+                self.worker.send(request);
+                self.progress = Some(0.);
+                self.state = State::Scores;
+                self.messages = vec!["Simulating...".to_string()];
+                self.report = None;
+            }
+            Msg::StopGame => {
+                if self.progress.take().is_none() {
+                    return false;
+                }
+                self.worker.send(simulator::Request::Cancel);
+                if let Some(report) = &self.report {
+                    self.messages = report_messages(report, None);
+                    self.messages
+                        .push("Stopped early, so these are the results so far.".to_string());
+                }
+            }
+            Msg::StartTraining(chart) => {
+                self.chart = chart;
+                self.state = State::Training;
+            }
+            Msg::Simulator(simulator::Response::Progress(progress, report)) => {
+                if self.progress.is_none() {
+                    return false;
+                }
+                self.progress = Some(progress);
+                self.messages = report_messages(&report, self.progress);
+                self.report = Some(report);
+            }
+            Msg::Simulator(simulator::Response::Done(report)) => {
+                if self.progress.take().is_none() {
+                    return false;
+                }
+                self.messages = report_messages(&report, None);
+                if let blackjack::Report::Run(outcome) = &report {
+                    self.record_score(outcome);
+                }
+                self.report = Some(report);
+            }
+            Msg::Submitted(result) => {
+                self.submission = None;
+                self.messages.push(match result {
+                    Ok(()) => "Your score is on the server's leaderboard too.".to_string(),
+                    Err(error) => {
+                        format!("The leaderboard server didn't take your score: {}", error)
+                    }
+                });
+            }
+            Msg::AssignLink(comp) => {
+                self.chooser_link = Some(comp);
+            }
+        }
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class="model",>
+                <div class=("container-header", "container", "curved"),>
+                    <h class="header">{ "💰💰💰 Blackjack Simulator 💰💰💰" }</h>
+                </div>
+                <div class=("container-main", "container", "curved"),>
+                    {
+                        match self.state {
+                            State::Choosing(choose_state) => html! {
+                                <Chooser: state=&choose_state, onsignal=self.link.callback(|comp| Msg::AssignLink(comp)) on_end_game=self.link.callback(|msg| msg),/>
+                            },
+                            State::Scores => html! {
+                                <div class=("container-feature", "container", "container-scores"),>
+                                    <Messages: messages=&self.messages,/>
+                                    <div class=("container", "scores-charts"),>
+                                        { self.view_charts() }
+                                    </div>
+                                </div>
+                            },
+                            State::HighScores => html! {
+                                <HighScores: />
+                            },
+                            State::Playing => html! {
+                                <Play: />
+                            },
+                            State::Training => html! {
+                                <Trainer: chart=self.chart.as_ref().map(|chart| chart.strategy.clone()), rules=self.chart.as_ref().map_or_else(blackjack::Rules::default, |chart| chart.rules),/>
+                            },
+                            State::Counting => html! {
+                                <CountDrill: />
+                            },
+                            _ => html! {
+                                <div class=("container-feature", "container"),>
+                                    <Messages: messages=&self.messages,/>
+                                </div>
+                            }
+                        }
+                    }
+                    <Controls: state=&self.state, onsignal=self.link.callback(|msg| msg) progress=self.progress, chooser_link=match &self.chooser_link.as_ref() {
+                        &None => None,
+                        &Some(comp) => Some(comp.clone())
+                    },/>
+                </div>
+            </div>
+        }
+    }
+}
+
+const SESSION_BINS: isize = 20;
+/// Differing cells listed under a head to head
+const DIFFERING_SHOWN: usize = 20;
+
+impl Model {
+    /// Puts a finished long run on the leaderboard, and sends it to the
+    /// leaderboard server if there is one.
+    fn record_score(&mut self, outcome: &blackjack::Outcome) {
+        let mut scores = Scores::open();
+        let strategy = match self.run_strategy.take() {
+            Some(strategy) => strategy,
+            None => return,
+        };
+        let date = Date::new().to_iso_string();
+        let entry = match blackjack::ScoreEntry::new(
+            outcome,
+            strategy,
+            scores.board.player.clone(),
+            date,
+        ) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let rank = blackjack::rank(&scores.board.entries, &entry)
+            .iter()
+            .filter(|other| other.score >= entry.score)
+            .count()
+            + 1;
+        self.messages.push(format!(
+            "That's #{} on your High Scores for these rules.",
+            rank
+        ));
+        if let Some(server) = scores.server() {
+            self.submission = leaderboard::submit(
+                &mut self.fetch,
+                server,
+                &entry,
+                self.link.callback(Msg::Submitted),
+            );
+        }
+        scores.record(entry);
+    }
+
+    fn view_charts(&self) -> Html {
+        match &self.report {
+            Some(blackjack::Report::Run(outcome)) => html! {
+                <>
+                <Chart: title="Bankroll".to_string(), line=&outcome.trajectory, sample_every=outcome.sample_every, rounds=outcome.rounds,/>
+                { view_histograms(&outcome.histograms) }
+                </>
+            },
+            Some(blackjack::Report::Sessions(sessions)) => {
+                let band = |i: usize| sessions.bands.iter().map(|b| b[i]).collect::<Vec<_>>();
+                html! {
+                    <>
+                    <Chart: title="Bankroll (5-95% and 25-75% of sessions)".to_string(), line=band(2), bands=vec![(band(0), band(4)), (band(1), band(3))], sample_every=sessions.sample_every, rounds=sessions.config.rounds,/>
+                    <Histogram: title="Session results ($)".to_string(), bins=session_bins(sessions),/>
+                    { view_histograms(&sessions.histograms) }
+                    </>
+                }
+            }
+            Some(blackjack::Report::Comparison(_)) | None => html! {<span />},
+        }
+    }
+}
+
+fn view_histograms(histograms: &blackjack::Histograms) -> Html {
+    let net_bins = histograms
+        .net_units
+        .iter()
+        .enumerate()
+        .map(|(i, &n)| (format!("{:+}", i as isize - blackjack::MAX_NET_UNITS), n))
+        .collect::<Vec<_>>();
+    html! {
+        <>
+        <Histogram: title="Net units per round".to_string(), bins=net_bins,/>
+        <Histogram: title="Player hand totals".to_string(), bins=total_bins(&histograms.player_totals, 4),/>
+        <Histogram: title="Dealer hand totals".to_string(), bins=total_bins(&histograms.dealer_totals, 17),/>
+        </>
+    }
+}
+
+fn total_bins(totals: &[usize], from: usize) -> Vec<(String, usize)> {
+    totals
+        .iter()
+        .enumerate()
+        .skip(from)
+        .map(|(total, &n)| match total {
+            22 => ("Bust".to_string(), n),
+            _ => (total.to_string(), n),
+        })
+        .collect()
+}
+
+/// Buckets each session's profit or loss into about `SESSION_BINS` bars.
+fn session_bins(sessions: &blackjack::SessionReport) -> Vec<(String, usize)> {
+    let bankroll = sessions.config.bankroll as isize;
+    let results: Vec<isize> = sessions
+        .final_bankrolls
+        .iter()
+        .map(|&balance| balance as isize - bankroll)
+        .collect();
+    let (min, max) = match (results.first(), results.last()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => return Vec::new(),
+    };
+    let width = (max - min) / SESSION_BINS + 1;
+    let mut bins = vec![0; ((max - min) / width + 1) as usize];
+    for result in results {
+        bins[((result - min) / width) as usize] += 1;
+    }
+    bins.into_iter()
+        .enumerate()
+        .map(|(i, n)| (format!("{:+}", min + i as isize * width), n))
+        .collect()
+}
+
+/// Summary of `report`, headed by how far along the run is if it's still going.
+fn report_messages(report: &blackjack::Report, progress: Option<f64>) -> Vec<String> {
+    let header = match progress {
+        Some(progress) => format!("Simulating... {:.0}%", progress * 100.),
+        None => "Good job!!".to_string(),
+    };
+    match report {
+        blackjack::Report::Run(outcome) => {
+            let mut messages = vec![
+                header,
+                format!(
+                    "Score: {}",
+                    outcome.balance as isize - blackjack::STARTING_MONEY as isize
+                ),
+                format!("Bet sizing: {}", outcome.bet_sizing),
+                format!("Rounds played: {}", outcome.rounds),
+                ev_message(&outcome.ev),
+                format!("Growth per round: {:.5}%", outcome.growth_rate * 100.),
+                format!("Max drawdown: ${}", outcome.max_drawdown),
+            ];
+            if let blackjack::Stopping::Precision {
+                target,
+                seconds,
+                max_rounds,
+            } = outcome.stopping
+            {
+                messages.push(if outcome.timed_out {
+                    format!(
+                        "Ran out of time after {}s, short of the ±{:.3}% target",
+                        seconds,
+                        target * 100.
+                    )
+                } else if outcome.ev.std_error <= target {
+                    format!(
+                        "Hands needed for a ±{:.3}% standard error: {}",
+                        target * 100.,
+                        outcome.ev.rounds
+                    )
+                } else if outcome.rounds >= max_rounds {
+                    format!(
+                        "Stopped after {} hands, short of the ±{:.3}% target",
+                        max_rounds,
+                        target * 100.
+                    )
+                } else {
+                    format!("Aiming for a ±{:.3}% standard error", target * 100.)
+                });
+            }
+            messages
+        }
+        blackjack::Report::Sessions(sessions) => {
+            let config = sessions.config;
+            let bankrolls = &sessions.final_bankrolls;
+            let drawdowns = &sessions.max_drawdowns;
+            vec![
+                header,
+                format!("Bet sizing: {}", sessions.bet_sizing),
+                format!(
+                    "{} sessions of up to {} rounds, starting with ${}",
+                    config.sessions, config.rounds, config.bankroll
+                ),
+                ev_message(&sessions.ev),
+                format!("Risk of ruin: {:.2}%", sessions.risk_of_ruin * 100.),
+                format!(
+                    "Chance of reaching the win goal: {:.2}%",
+                    sessions.goal_probability * 100.
+                ),
+                format!(
+                    "Final bankroll (5% / 25% / 50% / 75% / 95%): ${} / ${} / ${} / ${} / ${}",
+                    blackjack::percentile(bankrolls, 5.),
+                    blackjack::percentile(bankrolls, 25.),
+                    blackjack::percentile(bankrolls, 50.),
+                    blackjack::percentile(bankrolls, 75.),
+                    blackjack::percentile(bankrolls, 95.),
+                ),
+                format!(
+                    "Max drawdown (50% / 95%): ${} / ${}",
+                    blackjack::percentile(drawdowns, 50.),
+                    blackjack::percentile(drawdowns, 95.),
+                ),
+            ]
+        }
+        blackjack::Report::Comparison(comparison) => {
+            let names = &comparison.names;
+            let mut messages = vec![
+                header,
+                format!(
+                    "{} rounds each on the same cards (seed {})",
+                    comparison.rounds, comparison.seed
+                ),
+                format!("Bet sizing: {}", comparison.bet_sizing),
+            ];
+            messages.extend(
+                names
+                    .iter()
+                    .zip(comparison.ev.iter())
+                    .map(|(name, ev)| format!("{}: {}", name, ev_message(ev))),
+            );
+            for (name, difference) in names.iter().zip(comparison.difference.iter()).skip(1) {
+                let verdict = if difference.mean.abs() <= difference.margin() {
+                    "too close to call yet"
+                } else if difference.mean > 0. {
+                    "better"
+                } else {
+                    "worse"
+                };
+                messages.push(format!(
+                    "{} vs {}: {:+.3}% ± {:.3}% per round, {}",
+                    name,
+                    names[0],
+                    difference.mean * 100.,
+                    difference.margin() * 100.,
+                    verdict
+                ));
+            }
+            messages.push(format!(
+                "They differ in {} cells ({}):",
+                comparison.differing.len(),
+                names.join(" / ")
+            ));
+            messages.extend(
+                comparison
+                    .differing
+                    .iter()
+                    .take(DIFFERING_SHOWN)
+                    .map(|(cell, codes)| format!("{}: {}", cell, codes.join(" / "))),
+            );
+            if comparison.differing.len() > DIFFERING_SHOWN {
+                messages.push(format!(
+                    "...and {} more",
+                    comparison.differing.len() - DIFFERING_SHOWN
+                ));
+            }
+            messages
+        }
+    }
+}
+
+fn ev_message(ev: &blackjack::Estimate) -> String {
+    format!(
+        "EV per round: {:+.3}% ± {:.3}% of the bet (95%)",
+        ev.mean * 100.,
+        ev.margin() * 100.
+    )
+}
@@ -0,0 +1,489 @@
+// Blackjack from a terminal. `simulate` runs a strategy headlessly for
+// scripts, `compare` plays several head to head on the same cards, `play`
+// deals hands to play yourself, and `export` writes a chart out for sharing:
+//
+//     bjo simulate <chart> [--rules <rules.json>] [--bets <sizing>]
+//         [--hands <n>] [--threads <n>] [--seed <n>] [--format text|json]
+//     bjo compare <chart> <chart>... [--rules <rules.json>] [--bets <sizing>]
+//         [--hands <n>] [--seed <n>] [--format text|json]
+//     bjo play [--rules <rules.json>] [--bankroll <n>] [--seed <n>]
+//         [--hint [<chart>]]
+//     bjo export [<chart>] [--rules <rules.json>] [--format json|csv]
+//
+// Charts can be JSON or CSV, as described on `blackjack::ChartFile`, and
+// bring the rules they were built for unless `--rules` says otherwise.
+use bjo::blackjack::{
+    self, Action, BetSizing, ChartFile, HandView, Rules, Strategy, Table, TableError,
+};
+use std::io::{self, BufRead, Write};
+use std::{env, fs, iter, process, slice};
+
+const USAGE: &str = "Usage:
+  bjo simulate <chart> [--rules <rules.json>] [--bets flat|ramp|kelly|half-kelly|quarter-kelly] [--hands <n>] [--threads <n>] [--seed <n>] [--format text|json]
+  bjo compare <chart> <chart>... [--rules <rules.json>] [--bets flat|ramp|kelly|half-kelly|quarter-kelly] [--hands <n>] [--seed <n>] [--format text|json]
+  bjo play [--rules <rules.json>] [--bankroll <n>] [--seed <n>] [--hint [<chart>]]
+  bjo export [<chart>] [--rules <rules.json>] [--format json|csv]
+Charts are JSON or CSV files. Export with no chart writes basic strategy for the rules.";
+const DEFAULT_HANDS: usize = 1_000_000;
+const DEFAULT_BANKROLL: usize = 1_000;
+const DEFAULT_BET: usize = 10;
+
+#[derive(Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl Format {
+    fn name(self) -> &'static str {
+        match self {
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Csv => "csv",
+        }
+    }
+}
+
+struct Options {
+    strategy: Strategy,
+    rules: Rules,
+    bet_sizing: BetSizing,
+    hands: usize,
+    threads: usize,
+    seed: u64,
+    format: Format,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("simulate") => parse_options(&args[1..]).and_then(|options| simulate(&options)),
+        Some("compare") => compare(&args[1..]),
+        Some("play") => parse_play_options(&args[1..]).and_then(|options| play(&options)),
+        Some("export") => export(&args[1..]),
+        Some("help") | Some("--help") | Some("-h") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(2);
+    }
+}
+
+type Values<'a> = iter::Peekable<slice::Iter<'a, String>>;
+
+/// What the commands' arguments have in common: chart paths, `--rules` and
+/// `--format`.
+struct Args {
+    charts: Vec<(String, ChartFile)>,
+    rules: Option<Rules>,
+    format: Option<Format>,
+}
+
+impl Args {
+    /// Reads up to `max_charts` charts and one of `formats`. Any other flag
+    /// goes to `flag`, which takes its value from `values` and returns false
+    /// if it doesn't know the flag either.
+    fn parse<'a>(
+        args: &'a [String],
+        max_charts: usize,
+        formats: &[Format],
+        mut flag: impl FnMut(&'a str, &mut Values<'a>) -> Result<bool, String>,
+    ) -> Result<Args, String> {
+        let mut parsed = Args {
+            charts: Vec::new(),
+            rules: None,
+            format: None,
+        };
+        let mut values = args.iter().peekable();
+        while let Some(arg) = values.next() {
+            match arg.as_str() {
+                "--rules" => parsed.rules = Some(read_json(value(arg, &mut values)?)?),
+                "--format" if !formats.is_empty() => {
+                    let name = value(arg, &mut values)?;
+                    match formats.iter().find(|format| format.name() == name) {
+                        Some(&format) => parsed.format = Some(format),
+                        None => return Err(usage(format!("Unknown format {:?}", name))),
+                    }
+                }
+                path if parsed.charts.len() < max_charts && !path.starts_with("--") => {
+                    parsed.charts.push((path.to_string(), read_chart(path)?))
+                }
+                other => {
+                    if !flag(other, &mut values)? {
+                        return Err(usage(format!("Unexpected argument {:?}", other)));
+                    }
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn value<'a>(flag: &str, values: &mut Values<'a>) -> Result<&'a str, String> {
+    values
+        .next()
+        .map(String::as_str)
+        .ok_or_else(|| usage(format!("Missing value for {}", flag)))
+}
+
+fn usage(message: String) -> String {
+    format!("{}\n{}", message, USAGE)
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut bet_sizing = BetSizing::default();
+    let mut hands = DEFAULT_HANDS;
+    let mut threads = 1;
+    let mut seed = rand::random();
+    let args = Args::parse(args, 1, &[Format::Text, Format::Json], |flag, values| {
+        match flag {
+            "--bets" => bet_sizing = parse_bet_sizing(value(flag, values)?)?,
+            "--hands" => hands = parse_number(flag, value(flag, values)?)?,
+            "--threads" => threads = parse_number(flag, value(flag, values)?)?,
+            "--seed" => seed = parse_number(flag, value(flag, values)?)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+
+    let (_, chart) = args
+        .charts
+        .into_iter()
+        .next()
+        .ok_or_else(|| usage("Missing strategy file".to_string()))?;
+    Ok(Options {
+        rules: args.rules.unwrap_or(chart.rules),
+        strategy: chart.strategy,
+        bet_sizing,
+        hands,
+        threads,
+        seed,
+        format: args.format.unwrap_or(Format::Text),
+    })
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> Result<T, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+    serde_json::from_str(&text).map_err(|e| format!("Couldn't parse {}: {}", path, e))
+}
+
+fn read_chart(path: &str) -> Result<ChartFile, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+    ChartFile::parse(&text).map_err(|e| format!("Couldn't parse {}: {}", path, e))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, not {:?}", flag, value))
+}
+
+fn parse_bet_sizing(value: &str) -> Result<BetSizing, String> {
+    match value {
+        "flat" => Ok(BetSizing::Flat),
+        "ramp" => Ok(BetSizing::Ramp),
+        "kelly" | "full-kelly" => Ok(BetSizing::FullKelly),
+        "half-kelly" => Ok(BetSizing::HalfKelly),
+        "quarter-kelly" => Ok(BetSizing::QuarterKelly),
+        other => Err(usage(format!("Unknown bet sizing {:?}", other))),
+    }
+}
+
+fn simulate(options: &Options) -> Result<(), String> {
+    let report = blackjack::play_parallel(
+        &options.strategy,
+        &options.rules,
+        options.bet_sizing,
+        options.hands,
+        options.threads,
+        options.seed,
+    );
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let written = match options.format {
+        Format::Json => {
+            let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+            writeln!(out, "{}", json)
+        }
+        Format::Text | Format::Csv => print_report(&mut out, &report),
+    };
+    finish_output(written)
+}
+
+/// Turns a failed write to stdout into an error, unless the reader just
+/// stopped listening, as `bjo simulate ... | head` does.
+fn finish_output(written: io::Result<()>) -> Result<(), String> {
+    match written {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+            Err(format!("Couldn't write the output: {}", e))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn print_report(out: &mut impl Write, report: &blackjack::BatchReport) -> io::Result<()> {
+    writeln!(out, "Rules: {}", report.rules)?;
+    writeln!(out, "Bet sizing: {}", report.bet_sizing)?;
+    writeln!(
+        out,
+        "Rounds played: {} on {} threads (seed {})",
+        report.rounds, report.threads, report.seed
+    )?;
+    writeln!(
+        out,
+        "EV per round: {:+.3}% ± {:.3}% of the bet (95%)",
+        report.ev.mean * 100.,
+        report.ev.margin() * 100.
+    )?;
+    writeln!(out, "Rebuys: {}", report.rebuys)?;
+    writeln!(out, "Net units per round:")?;
+    let rounds = report.rounds.max(1) as f64;
+    for (i, &n) in report.histograms.net_units.iter().enumerate() {
+        if n > 0 {
+            writeln!(
+                out,
+                "  {:+3}: {:6.2}%",
+                i as isize - blackjack::MAX_NET_UNITS,
+                n as f64 / rounds * 100.
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Plays the charts head to head, each measured against the first.
+fn compare(args: &[String]) -> Result<(), String> {
+    let mut bet_sizing = BetSizing::default();
+    let mut hands = DEFAULT_HANDS;
+    let mut seed = rand::random();
+    let args = Args::parse(
+        args,
+        usize::MAX,
+        &[Format::Text, Format::Json],
+        |flag, values| {
+            match flag {
+                "--bets" => bet_sizing = parse_bet_sizing(value(flag, values)?)?,
+                "--hands" => hands = parse_number(flag, value(flag, values)?)?,
+                "--seed" => seed = parse_number(flag, value(flag, values)?)?,
+                _ => return Ok(false),
+            }
+            Ok(true)
+        },
+    )?;
+    if args.charts.len() < 2 {
+        return Err(usage("Compare needs at least two charts".to_string()));
+    }
+
+    let rules = args.rules.unwrap_or(args.charts[0].1.rules);
+    let entrants = args
+        .charts
+        .into_iter()
+        .map(|(path, chart)| (path, chart.strategy))
+        .collect();
+    let report = blackjack::compare(entrants, &rules, bet_sizing, hands, seed);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let written = match args.format.unwrap_or(Format::Text) {
+        Format::Json => {
+            let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+            writeln!(out, "{}", json)
+        }
+        Format::Text | Format::Csv => print_comparison(&mut out, &report),
+    };
+    finish_output(written)
+}
+
+fn print_comparison(out: &mut impl Write, report: &blackjack::ComparisonReport) -> io::Result<()> {
+    writeln!(out, "Rules: {}", report.rules)?;
+    writeln!(out, "Bet sizing: {}", report.bet_sizing)?;
+    writeln!(
+        out,
+        "Rounds played: {} each on the same cards (seed {})",
+        report.rounds, report.seed
+    )?;
+    let names = &report.names;
+    for (name, ev) in names.iter().zip(report.ev.iter()) {
+        writeln!(
+            out,
+            "{}: EV per round {:+.3}% ± {:.3}%",
+            name,
+            ev.mean * 100.,
+            ev.margin() * 100.
+        )?;
+    }
+    for (name, difference) in names.iter().zip(report.difference.iter()).skip(1) {
+        writeln!(
+            out,
+            "{} - {}: {:+.3}% ± {:.3}% per round (95%)",
+            name,
+            names[0],
+            difference.mean * 100.,
+            difference.margin() * 100.
+        )?;
+    }
+    writeln!(out, "Differing cells ({}):", names.join(" / "))?;
+    for (cell, codes) in report.differing.iter() {
+        writeln!(out, "  {}: {}", cell, codes.join(" / "))?;
+    }
+    Ok(())
+}
+
+struct PlayOptions {
+    rules: Rules,
+    bankroll: usize,
+    seed: Option<u64>,
+    hint: Option<Strategy>,
+}
+
+fn parse_play_options(args: &[String]) -> Result<PlayOptions, String> {
+    let mut bankroll = DEFAULT_BANKROLL;
+    let mut seed = None;
+    // Without a chart, the hint follows the rules, which may come later
+    let mut hint_chart = None;
+    let args = Args::parse(args, 0, &[], |flag, values| {
+        match flag {
+            "--bankroll" => bankroll = parse_number(flag, value(flag, values)?)?,
+            "--seed" => seed = Some(parse_number(flag, value(flag, values)?)?),
+            "--hint" => {
+                hint_chart = Some(match values.peek() {
+                    Some(path) if !path.starts_with("--") => {
+                        Some(read_chart(values.next().unwrap())?.strategy)
+                    }
+                    _ => None,
+                })
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+
+    let rules = args.rules.unwrap_or_default();
+    Ok(PlayOptions {
+        hint: hint_chart.map(|chart| chart.unwrap_or_else(|| Strategy::optimal(&rules))),
+        rules,
+        bankroll,
+        seed,
+    })
+}
+
+fn play(options: &PlayOptions) -> Result<(), String> {
+    let mut table = match options.seed {
+        Some(seed) => Table::with_seed(options.rules, options.bankroll, seed),
+        None => Table::new(options.rules, options.bankroll),
+    };
+    let mut bet = DEFAULT_BET;
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut prompt = |text: &str| -> Option<String> {
+        print!("{}", text);
+        io::stdout().flush().ok()?;
+        lines.next()?.ok().map(|line| line.trim().to_lowercase())
+    };
+
+    loop {
+        println!();
+        println!("Bankroll: ${}", table.balance());
+        if table.balance() == 0 {
+            println!("You're broke!");
+            return Ok(());
+        }
+        let line = match prompt(&format!("Bet (Enter for ${}, q to quit): ", bet)) {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        match line.as_str() {
+            "q" => return Ok(()),
+            "" => (),
+            amount => match amount.parse() {
+                Ok(amount) => bet = amount,
+                Err(_) => {
+                    println!("That's not a bet");
+                    continue;
+                }
+            },
+        }
+        if let Err(e) = table.deal(bet) {
+            println!("{}", e);
+            continue;
+        }
+
+        while let Some(active) = table.active_hand() {
+            if let Some(dealer) = table.dealer() {
+                println!("Dealer shows: {}", show_hand(&dealer));
+            }
+            let hands = table.hands();
+            for (i, hand) in hands.iter().enumerate() {
+                let marker = if i == active { ">" } else { " " };
+                println!("{} Your hand: {}", marker, show_hand(hand));
+            }
+            let actions = table.actions();
+            let choices: Vec<String> = actions
+                .iter()
+                .map(|action| format!("[{}] {}", action.key(), action))
+                .collect();
+            let hint = match options.hint.as_ref().and_then(|s| table.hint(s)) {
+                Some(action) => format!(" (hint: {})", action),
+                None => String::new(),
+            };
+            let line = match prompt(&format!("{}{}: ", choices.join("  "), hint)) {
+                Some(line) => line,
+                None => return Ok(()),
+            };
+            // "sp" was the old shorthand for splitting
+            let action = match line.as_str() {
+                "q" => return Ok(()),
+                "sp" => Some(Action::Split),
+                line => line.chars().next().and_then(Action::from_key),
+            };
+            match action.map(|action| table.act(action)) {
+                Some(Ok(_)) => (),
+                Some(Err(e)) => println!("{}", e),
+                None => println!("{}", TableError::IllegalAction),
+            }
+        }
+
+        if let Some(dealer) = table.dealer() {
+            println!("Dealer has: {}", show_hand(&dealer));
+        }
+        for hand in table.hands() {
+            if let Some((settlement, net)) = hand.settlement {
+                println!("{} - {} ({:+})", show_hand(&hand), settlement, net);
+            }
+        }
+    }
+}
+
+fn export(args: &[String]) -> Result<(), String> {
+    let args = Args::parse(args, 1, &[Format::Json, Format::Csv], |_, _| Ok(false))?;
+
+    let chart = match (args.charts.into_iter().next(), args.rules) {
+        (Some((_, chart)), Some(rules)) => ChartFile { rules, ..chart },
+        (Some((_, chart)), None) => chart,
+        (None, rules) => {
+            let rules = rules.unwrap_or_default();
+            ChartFile {
+                strategy: Strategy::optimal(&rules),
+                rules,
+            }
+        }
+    };
+    let text = match args.format {
+        Some(Format::Csv) => chart.to_csv(),
+        _ => chart.to_json(),
+    };
+    finish_output(io::stdout().lock().write_all(text.as_bytes()))
+}
+
+fn show_hand(hand: &HandView) -> String {
+    format!(
+        "{} ({}{})",
+        hand.cards.join(" "),
+        if hand.soft { "soft " } else { "" },
+        hand.total
+    )
+}
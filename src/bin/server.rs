@@ -0,0 +1,243 @@
+// Leaderboard server for the High Scores page, built with
+// `cargo run --bin bjo-server --no-default-features --features server`:
+//
+//     bjo-server [--addr <host:port>] [--db <path>] [--max-rounds <n>]
+//
+// `POST /scores` takes a `blackjack::ScoreEntry` and replays its run from the
+// seed before keeping it, so every score on the board is one the chart really
+// got. Replays run off the main thread, a few at a time, so one long run
+// doesn't hold up everyone else. `GET /scores` lists them all, best first.
+// Scores live in SQLite.
+//
+// Seeds are picked by the player's browser, not the server. Someone could play
+// many seeds offline and only send the lucky ones, and the replay can't tell,
+// so the board is only as fair as its players.
+use bjo::blackjack::{self, ScoreEntry};
+use rusqlite::{params, Connection, ErrorCode};
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{env, process, thread};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const USAGE: &str = "Usage:
+  bjo-server [--addr <host:port>] [--db <path>] [--max-rounds <n>]";
+const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+const DEFAULT_DB: &str = "scores.db";
+/// Largest request body read, in bytes. A chart and its rules are about 1.5KB.
+const MAX_BODY: u64 = 64 * 1024;
+/// Most submissions replayed at once. Each keeps a core busy until it's done.
+const MAX_REPLAYS: usize = 4;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS scores (
+    id INTEGER PRIMARY KEY,
+    player TEXT NOT NULL,
+    score INTEGER NOT NULL,
+    rounds INTEGER NOT NULL,
+    rules TEXT NOT NULL,
+    bet_sizing TEXT NOT NULL,
+    seed TEXT NOT NULL,
+    strategy TEXT NOT NULL,
+    date TEXT NOT NULL,
+    UNIQUE (rounds, rules, bet_sizing, seed, strategy)
+)";
+
+struct Options {
+    addr: String,
+    db: String,
+    max_rounds: usize,
+}
+
+fn main() {
+    let result = parse_options(&env::args().skip(1).collect::<Vec<_>>()).and_then(|o| serve(&o));
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(2);
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        addr: DEFAULT_ADDR.to_string(),
+        db: DEFAULT_DB.to_string(),
+        max_rounds: blackjack::LONG_RUN_ROUNDS,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--addr" => options.addr = value()?.clone(),
+            "--db" => options.db = value()?.clone(),
+            "--max-rounds" => {
+                let value = value()?;
+                options.max_rounds = value
+                    .parse()
+                    .map_err(|_| format!("{} expects a number, not {:?}", arg, value))?;
+            }
+            "help" | "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            other => return Err(format!("Unexpected argument {:?}\n{}", other, USAGE)),
+        }
+    }
+    Ok(options)
+}
+
+fn serve(options: &Options) -> Result<(), String> {
+    let db = Connection::open(&options.db)
+        .and_then(|db| db.execute_batch(SCHEMA).map(|_| db))
+        .map_err(|e| format!("Couldn't open {}: {}", options.db, e))?;
+    let server = Server::http(&options.addr)
+        .map_err(|e| format!("Couldn't listen on {}: {}", options.addr, e))?;
+    println!("Leaderboard on http://{}/scores", options.addr);
+
+    let replays = Arc::new(AtomicUsize::new(0));
+    for request in server.incoming_requests() {
+        let (status, body) = match (request.method(), request.url()) {
+            (Method::Options, _) => (204, String::new()),
+            (Method::Get, "/scores") => match scores(&db) {
+                Ok(entries) => (200, to_json(&entries)),
+                Err(e) => (500, e.to_string()),
+            },
+            (Method::Post, "/scores") => {
+                if replays.fetch_add(1, Ordering::SeqCst) < MAX_REPLAYS {
+                    submit_in_background(request, Arc::clone(&replays), options);
+                    continue;
+                }
+                replays.fetch_sub(1, Ordering::SeqCst);
+                (503, "Busy checking scores, try again soon".to_string())
+            }
+            _ => (404, "Not found".to_string()),
+        };
+        if let Err(e) = respond(request, status, body) {
+            eprintln!("Couldn't respond: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Runs `submit` on its own thread, with its own connection, and replies from
+/// there. `replays` is counted down once the score has been checked.
+fn submit_in_background(request: Request, replays: Arc<AtomicUsize>, options: &Options) {
+    let path = options.db.clone();
+    let max_rounds = options.max_rounds;
+    thread::spawn(move || {
+        let mut request = request;
+        let (status, body) = match Connection::open(&path) {
+            Ok(db) => submit(&db, &mut request, max_rounds),
+            Err(e) => (500, e.to_string()),
+        };
+        replays.fetch_sub(1, Ordering::SeqCst);
+        if let Err(e) = respond(request, status, body) {
+            eprintln!("Couldn't respond: {}", e);
+        }
+    });
+}
+
+/// Checks and keeps a submitted score. Returns the status and body to reply with.
+fn submit(db: &Connection, request: &mut Request, max_rounds: usize) -> (u16, String) {
+    let mut text = String::new();
+    if let Err(e) = request.as_reader().take(MAX_BODY).read_to_string(&mut text) {
+        return (400, e.to_string());
+    }
+    let entry: ScoreEntry = match serde_json::from_str(&text) {
+        Ok(entry) => entry,
+        Err(e) => return (400, format!("That isn't a score: {}", e)),
+    };
+    if let Err(e) = entry.verify(max_rounds) {
+        return (422, e);
+    }
+    let inserted = db.execute(
+        "INSERT INTO scores (player, score, rounds, rules, bet_sizing, seed, strategy, date)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            entry.player,
+            entry.score as i64,
+            entry.rounds as i64,
+            to_json(&entry.rules),
+            to_json(&entry.bet_sizing),
+            entry.seed.to_string(),
+            to_json(&entry.strategy),
+            entry.date,
+        ],
+    );
+    match inserted {
+        Ok(_) => (201, to_json(&entry)),
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::ConstraintViolation => {
+            (409, "That run is already on the leaderboard".to_string())
+        }
+        Err(e) => (500, e.to_string()),
+    }
+}
+
+/// Every score kept, best first. Rows that don't read back as a score, say
+/// from an older version of the server, are logged and left out.
+fn scores(db: &Connection) -> rusqlite::Result<Vec<ScoreEntry>> {
+    let mut statement = db.prepare(
+        "SELECT player, score, rounds, rules, bet_sizing, seed, strategy, date, id
+         FROM scores ORDER BY score DESC, id",
+    )?;
+    let rows = statement.query_map([], |row| {
+        let id: i64 = row.get(8)?;
+        let column = |i: usize| row.get::<_, String>(i).map_err(|e| e.to_string());
+        let number = |i: usize| row.get::<_, i64>(i).map_err(|e| e.to_string());
+        let entry = (|| -> Result<ScoreEntry, String> {
+            Ok(ScoreEntry {
+                player: column(0)?,
+                score: number(1)? as isize,
+                rounds: number(2)? as usize,
+                rules: from_json(&column(3)?)?,
+                bet_sizing: from_json(&column(4)?)?,
+                seed: column(5)?.parse().map_err(|e| format!("bad seed: {}", e))?,
+                strategy: from_json(&column(6)?)?,
+                date: column(7)?,
+            })
+        })();
+        Ok((id, entry))
+    })?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        match row? {
+            (_, Ok(entry)) => entries.push(entry),
+            (id, Err(e)) => eprintln!("Skipping score {}: {}", id, e),
+        }
+    }
+    Ok(entries)
+}
+
+fn respond(request: Request, status: u16, body: String) -> std::io::Result<()> {
+    let content_type = if status < 300 {
+        "application/json"
+    } else {
+        "text/plain; charset=utf-8"
+    };
+    // The page is served from somewhere else, usually another port on
+    // localhost, so the browser needs to be told it may call in
+    let headers = [
+        ("Content-Type", content_type),
+        ("Access-Control-Allow-Origin", "*"),
+        ("Access-Control-Allow-Methods", "GET, POST, OPTIONS"),
+        ("Access-Control-Allow-Headers", "Content-Type"),
+    ];
+    let response = headers.iter().fold(
+        Response::from_string(body).with_status_code(status),
+        |response, (name, value)| {
+            response.with_header(Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap())
+        },
+    );
+    request.respond(response)
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("scores always serialize")
+}
+
+/// Reads back a column this server wrote with `to_json`.
+fn from_json<T: serde::de::DeserializeOwned>(text: &str) -> Result<T, String> {
+    serde_json::from_str(text).map_err(|e| e.to_string())
+}
@@ -0,0 +1,10 @@
+// Entry point for the simulation Web Worker, served next to the app as
+// `simulator.js` (`cargo web deploy --bin simulator`).
+use bjo::simulator::Simulator;
+use yew::agent::Threaded;
+
+fn main() {
+    yew::initialize();
+    Simulator::register();
+    yew::run_loop();
+}
@@ -0,0 +1,322 @@
+// Full-screen terminal table for playing against the engine, built with
+// `cargo run --bin bjo-tui --no-default-features --features tui`:
+//
+//     bjo-tui [--rules <rules.json>] [--bankroll <n>] [--seed <n>] [--hint [<chart>]]
+//
+// The flags are the ones `bjo play` takes. `--hint` starts with hints showing,
+// from the chart if one is given and otherwise basic strategy for the rules.
+use bjo::blackjack::{Action, ChartFile, HandView, Rules, Strategy, Table};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::{Color, Print, ResetColor, SetForegroundColor},
+    terminal::{self, ClearType},
+};
+use std::io::{self, Write};
+use std::{env, fs, process};
+
+const USAGE: &str = "Usage:
+  bjo-tui [--rules <rules.json>] [--bankroll <n>] [--seed <n>] [--hint [<chart>]]";
+const BANKROLL: usize = 1_000;
+const CHIPS: [usize; 4] = [1, 5, 25, 100];
+
+struct Options {
+    rules: Rules,
+    bankroll: usize,
+    seed: Option<u64>,
+    /// Whether hints start out showing
+    hint: bool,
+    chart: Option<Strategy>,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        rules: Rules::default(),
+        bankroll: BANKROLL,
+        seed: None,
+        hint: false,
+        chart: None,
+    };
+
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--rules" => {
+                let path = value()?;
+                options.rules = serde_json::from_str(&read(path)?)
+                    .map_err(|e| format!("Couldn't parse {}: {}", path, e))?;
+            }
+            "--bankroll" => options.bankroll = parse_number(arg, value()?)?,
+            "--seed" => options.seed = Some(parse_number(arg, value()?)?),
+            "--hint" => {
+                options.hint = true;
+                if let Some(path) = args.next_if(|path| !path.starts_with("--")) {
+                    let chart = ChartFile::parse(&read(path)?)
+                        .map_err(|e| format!("Couldn't parse {}: {}", path, e))?;
+                    options.chart = Some(chart.strategy);
+                }
+            }
+            "help" | "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            other => return Err(format!("Unexpected argument {:?}\n{}", other, USAGE)),
+        }
+    }
+    Ok(options)
+}
+
+fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path, e))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, not {:?}", flag, value))
+}
+
+struct App {
+    table: Table,
+    strategy: Strategy,
+    bet: usize,
+    hint: bool,
+    message: String,
+}
+
+impl App {
+    fn new(options: Options) -> Self {
+        let table = match options.seed {
+            Some(seed) => Table::with_seed(options.rules, options.bankroll, seed),
+            None => Table::new(options.rules, options.bankroll),
+        };
+        App {
+            table,
+            strategy: match options.chart {
+                Some(chart) => chart,
+                None => Strategy::optimal(&options.rules),
+            },
+            bet: CHIPS[1],
+            hint: options.hint,
+            message: "Place your bet with the chip keys, then press Enter to deal".to_string(),
+        }
+    }
+
+    /// Handles a key press. Returns false to quit.
+    fn key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('?') => self.hint = !self.hint,
+            _ if self.table.in_round() => self.play(code),
+            _ => self.bet(code),
+        }
+        true
+    }
+
+    fn bet(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c @ '1'..='4') => {
+                self.bet += CHIPS[c as usize - '1' as usize];
+                self.message = String::new();
+            }
+            KeyCode::Char('c') => self.bet = 0,
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                self.message = match self.table.deal(self.bet) {
+                    Ok(_) => String::new(),
+                    Err(e) => e.to_string(),
+                };
+                self.settled();
+            }
+            _ => (),
+        }
+    }
+
+    fn play(&mut self, code: KeyCode) {
+        let action = match code {
+            KeyCode::Char(c) => Action::from_key(c),
+            _ => None,
+        };
+        self.message = match action.map(|action| self.table.act(action)) {
+            Some(Ok(_)) => String::new(),
+            Some(Err(e)) => e.to_string(),
+            None => return,
+        };
+        self.settled();
+    }
+
+    /// Sums up the round once it's over.
+    fn settled(&mut self) {
+        if self.table.in_round() {
+            return;
+        }
+        let results: Vec<String> = self
+            .table
+            .hands()
+            .iter()
+            .filter_map(|hand| hand.settlement)
+            .map(|(settlement, net)| format!("{} ({:+})", settlement, net))
+            .collect();
+        if !results.is_empty() {
+            self.message = results.join(", ");
+        }
+    }
+
+    fn draw(&self, out: &mut impl Write) -> crossterm::Result<()> {
+        queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(2, 1))?;
+        queue!(
+            out,
+            SetForegroundColor(Color::Yellow),
+            Print("Blackjack"),
+            ResetColor,
+            cursor::MoveTo(40, 1),
+            Print(format!("Bankroll: ${}", self.table.balance())),
+        )?;
+
+        if let Some(dealer) = self.table.dealer() {
+            draw_hand(out, 3, "Dealer", &dealer, false)?;
+        }
+        let active = self.table.active_hand();
+        for (i, hand) in self.table.hands().iter().enumerate() {
+            let label = format!("Hand {}", i + 1);
+            draw_hand(out, 8 + 5 * i as u16, &label, hand, active == Some(i))?;
+        }
+
+        let rows = 8 + 5 * self.table.hands().len().max(1) as u16;
+        queue!(
+            out,
+            cursor::MoveTo(2, rows),
+            Print(format!(
+                "Shoe: {:.0}% dealt   Running count: {:+}   True count: {:+.1}",
+                self.table.penetration() * 100.,
+                self.table.running_count(),
+                self.table.true_count()
+            )),
+            cursor::MoveTo(2, rows + 2),
+        )?;
+
+        if self.table.in_round() {
+            let actions = self.table.actions();
+            for action in Action::ALL.iter() {
+                let color = if actions.contains(action) {
+                    Color::White
+                } else {
+                    Color::DarkGrey
+                };
+                queue!(
+                    out,
+                    SetForegroundColor(color),
+                    Print(format!("[{}] {}  ", action.key(), action)),
+                )?;
+            }
+            queue!(out, ResetColor)?;
+            if self.hint {
+                if let Some(hint) = self.table.hint(&self.strategy) {
+                    queue!(out, Print(format!(" Hint: {}", hint)))?;
+                }
+            }
+        } else {
+            queue!(out, Print(format!("Bet: ${}   Chips:", self.bet)))?;
+            for (i, chip) in CHIPS.iter().enumerate() {
+                queue!(out, Print(format!("  [{}] ${}", i + 1, chip)))?;
+            }
+            queue!(out, Print("   [c] Clear   [Enter] Deal"))?;
+        }
+
+        queue!(
+            out,
+            cursor::MoveTo(2, rows + 4),
+            SetForegroundColor(Color::Cyan),
+            Print(&self.message),
+            ResetColor,
+            cursor::MoveTo(2, rows + 6),
+            SetForegroundColor(Color::DarkGrey),
+            Print("[?] Toggle hints   [q] Quit"),
+            ResetColor,
+        )?;
+        Ok(out.flush()?)
+    }
+}
+
+/// Draws `hand` as a row of boxed cards with its total to the right.
+fn draw_hand(
+    out: &mut impl Write,
+    row: u16,
+    label: &str,
+    hand: &HandView,
+    active: bool,
+) -> crossterm::Result<()> {
+    let marker = if active { ">" } else { " " };
+    queue!(
+        out,
+        cursor::MoveTo(1, row + 1),
+        Print(format!("{}{:<8}", marker, label))
+    )?;
+    for (i, card) in hand.cards.iter().enumerate() {
+        let x = 12 + 6 * i as u16;
+        let color = if card == "?" {
+            Color::DarkGrey
+        } else {
+            Color::White
+        };
+        queue!(
+            out,
+            SetForegroundColor(color),
+            cursor::MoveTo(x, row),
+            Print("┌───┐"),
+            cursor::MoveTo(x, row + 1),
+            Print(format!("│{:^3}│", card)),
+            cursor::MoveTo(x, row + 2),
+            Print("└───┘"),
+            ResetColor,
+        )?;
+    }
+
+    let x = 14 + 6 * hand.cards.len() as u16;
+    let soft = if hand.soft { "soft " } else { "" };
+    queue!(
+        out,
+        cursor::MoveTo(x, row + 1),
+        Print(format!("{}{}", soft, hand.total))
+    )?;
+    if hand.bet > 0 {
+        queue!(out, Print(format!("   bet ${}", hand.bet)))?;
+    }
+    if let Some((settlement, net)) = hand.settlement {
+        queue!(out, Print(format!("   {} ({:+})", settlement, net)))?;
+    }
+    Ok(())
+}
+
+fn main() -> crossterm::Result<()> {
+    let options = match parse_options(&env::args().skip(1).collect::<Vec<_>>()) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    };
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    let mut app = App::new(options);
+    let result = (|| loop {
+        app.draw(&mut out)?;
+        if let Event::Key(KeyEvent { code, modifiers }) = event::read()? {
+            let interrupted =
+                modifiers.contains(KeyModifiers::CONTROL) && code == KeyCode::Char('c');
+            if interrupted || !app.key(code) {
+                return Ok(());
+            }
+        }
+    })();
+
+    execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
@@ -1,13 +1,22 @@
-use ndarray::Array2;
-use rand::{seq::SliceRandom, thread_rng};
+use ndarray::{arr2, Array2};
+use rand::{rngs::StdRng, seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
-const SHUFFLE_SIZE: usize = 260;
 const RESHUFFLE: usize = 18;
-const NUM_DECKS: usize = 6;
-const BUST_KWD: &str = &"bust";
-const STARTING_MONEY: usize = 1_000_000;
+const BUST_KWD: &str = "bust";
+/// What a long run starts with, in dollars
+pub const STARTING_MONEY: usize = 1_000_000;
 const NUM_LOOPS: usize = 50_000;
 const BET_UNIT: usize = 1;
+const BASE_EDGE: f64 = -0.005;
+const EDGE_PER_COUNT: f64 = 0.005;
+const HAND_VARIANCE: f64 = 1.33;
+const TRAJECTORY_POINTS: usize = 200;
+const MIN_PRECISION_ROUNDS: usize = 10_000;
+pub const MAX_NET_UNITS: isize = 8;
+/// Round cap for a `Stopping::Precision` run in the browser, in case the
+/// target can't be reached
+pub const MAX_PRECISION_ROUNDS: usize = 20_000_000;
 
 // --- CARDS ---
 
@@ -102,16 +111,24 @@ impl Cards {
 
 // --- DECK ---
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Deck {
     cards: Vec<Card>,
     drawables: Vec<Card>,
     counter: isize,
+    /// Reshuffle once this few cards are left
+    cut: usize,
+    rng: StdRng,
 }
 
 impl Deck {
-    fn new() -> Self {
-        let mut deck: Vec<Card> = (0..52 * NUM_DECKS).map(|_| Card::Def(1)).collect();
+    fn new(rules: &Rules) -> Self {
+        Deck::with_seed(rules, thread_rng().gen())
+    }
+
+    fn with_seed(rules: &Rules, seed: u64) -> Self {
+        let size = 52 * rules.decks.max(1);
+        let mut deck: Vec<Card> = (0..size).map(|_| Card::Def(1)).collect();
         const VALS: [Card; 13] = [
             Card::Maybe(1, 11),
             Card::Def(2),
@@ -127,33 +144,38 @@ impl Deck {
             Card::Def(10),
             Card::Def(10),
         ];
-        for i in 0..52 * NUM_DECKS {
+        for i in 0..size {
             deck[i] = VALS[i % 13];
         }
         Deck {
             cards: deck,
             drawables: Vec::new(),
             counter: 0,
+            cut: ((size as f64 * (1. - rules.penetration)) as usize).max(RESHUFFLE),
+            rng: StdRng::seed_from_u64(seed),
         }
     }
 
     fn shuffle(&mut self) {
-        self.drawables = self
-            .cards
-            .choose_multiple(&mut thread_rng(), SHUFFLE_SIZE)
-            .map(|&c| c)
-            .collect();
+        self.drawables = self.cards.clone();
+        self.drawables.shuffle(&mut self.rng);
         self.counter = 0;
     }
 
+    /// Whether the shoe has reached the cut card
+    fn needs_shuffle(&self) -> bool {
+        self.drawables.len() < self.cut
+    }
+
+    fn true_count(&self) -> f64 {
+        let decks_left = self.drawables.len() as f64 / 52.;
+        self.counter as f64 / decks_left
+    }
+
     fn draw(&mut self) -> Card {
         match self.drawables.pop() {
             Some(n) => {
-                self.counter += match n {
-                    Card::Maybe(_, _) | Card::Def(10) => 1,
-                    Card::Def(d) if d < 7 => -1,
-                    _ => 0,
-                };
+                self.counter += hi_lo(n);
                 n
             }
             None => panic!("Not enough cards!"),
@@ -161,6 +183,15 @@ impl Deck {
     }
 }
 
+/// Hi-Lo tag of a card: low cards leaving the shoe are good for the player
+fn hi_lo(card: Card) -> isize {
+    match card {
+        Card::Maybe(_, _) | Card::Def(10) => -1,
+        Card::Def(d) if d < 7 => 1,
+        _ => 0,
+    }
+}
+
 // --- HAND ---
 
 struct Hand {
@@ -181,6 +212,14 @@ impl Hand {
         }
     }
 
+    fn with_card(card: Card) -> Self {
+        Hand {
+            cards: Cards { cards: vec![card] },
+            sum: card,
+            busted: false,
+        }
+    }
+
     fn hit(&mut self, deck: &mut Deck) {
         let card = deck.draw();
         self.cards.cards.push(card);
@@ -197,10 +236,11 @@ struct Wallet {
 
 impl Wallet {
     fn new() -> Self {
-        Wallet {
-            balance: STARTING_MONEY,
-            bet: 0,
-        }
+        Wallet::with_balance(STARTING_MONEY)
+    }
+
+    fn with_balance(balance: usize) -> Self {
+        Wallet { balance, bet: 0 }
     }
 
     fn place_bet(&mut self, amount: usize) -> Result<(), ()> {
@@ -225,324 +265,3008 @@ impl Wallet {
     fn lose(&mut self) {
         self.bet = 0;
     }
+
+    fn credit(&mut self, amount: usize) {
+        self.balance += amount;
+    }
 }
 
-// --- PROGRAM ---
+// --- RULES ---
 
-fn choice(input: &str, deck: &mut Deck, hand: &mut Hand, wallet: &mut Wallet) -> bool {
-    match input {
-        "s" => return false,
-        "h" => hand.hit(deck),
-        "d" => {
-            match wallet.double() {
-                Ok(_) => {
-                    hand.hit(deck);
-                    // hand.show();
-                    return false;
-                }
-                Err(_) => {
-                    println!("Balance too low (${})", wallet.balance);
-                }
-            };
+/// Table rules that can vary between games
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rules {
+    pub decks: usize,
+    /// Fraction of the shoe dealt before reshuffling
+    pub penetration: f64,
+    pub dealer_hits_soft_17: bool,
+    /// Whether the player may give up half their bet instead of playing a
+    /// hand. Only offered at a `Table`, as the strategy matrices can't ask for it.
+    pub surrender: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            decks: 6,
+            penetration: 0.75,
+            dealer_hits_soft_17: false,
+            surrender: true,
         }
-        BUST_KWD => return false,
-        _ => (),
     }
-    true
-    // True means keep playing the round
 }
 
-#[allow(unused_assignments)]
-#[allow(unused_macros)]
-pub fn play(
-    rule_hard: &Array2<&'static str>,
-    rule_soft: &Array2<&'static str>,
-    rule_split: &Array2<&'static str>,
-) -> usize {
-    let mut wallet = Wallet::new();
+impl std::fmt::Display for Rules {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} decks, {:.0}% penetration, dealer {} soft 17{}",
+            self.decks,
+            self.penetration * 100.,
+            if self.dealer_hits_soft_17 {
+                "hits"
+            } else {
+                "stands on"
+            },
+            if self.surrender { ", surrender" } else { "" }
+        )
+    }
+}
 
-    let mut deck = Deck::new();
+// --- STRATEGY ---
 
-    let mut running_balance: Vec<usize> = Vec::new();
+/// Lowest total in the hard chart, a pair of 2s played as 4
+pub const LOWEST_HARD: usize = 4;
+/// Rows in the hard chart, one for each total from 4 to 21
+pub const HARD_CHART_ROWS: usize = 18;
 
-    'play: for _ in 0..NUM_LOOPS {
-        deck.shuffle();
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Strategy {
+    #[serde(
+        serialize_with = "matrix::serialize",
+        deserialize_with = "matrix::deserialize_hard"
+    )]
+    pub hard: Array2<&'static str>,
+    #[serde(with = "matrix")]
+    pub soft: Array2<&'static str>,
+    #[serde(with = "matrix")]
+    pub split: Array2<&'static str>,
+}
 
-        'main: loop {
-            let remaining_cards = deck.drawables.len();
-            if remaining_cards < RESHUFFLE {
-                break 'main;
-            }
+impl Strategy {
+    /// The chart the Chooser starts from
+    pub fn basic() -> Self {
+        Strategy {
+            hard: arr2(&[
+                ["h", "h", "h", "h", "h", "h", "h", "h", "h", "h"], // 4
+                ["h", "h", "h", "h", "h", "h", "h", "h", "h", "h"],
+                ["h", "h", "h", "h", "h", "h", "h", "h", "h", "h"],
+                ["h", "h", "h", "h", "h", "h", "h", "h", "h", "h"],
+                ["h", "h", "h", "h", "h", "h", "h", "h", "h", "h"], // 8
+                ["s", "d", "d", "d", "d", "s", "s", "s", "s", "s"], // 9
+                ["d", "d", "d", "d", "d", "d", "d", "d", "s", "s"], // 10
+                ["d", "d", "d", "d", "d", "d", "d", "d", "d", "d"], // 11
+                ["h", "h", "s", "s", "s", "h", "h", "h", "h", "h"],
+                ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
+                ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
+                ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
+                ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"], //16
+                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //17
+                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //18
+                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //19
+                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"],
+                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //21
+            ]),
+            soft: arr2(&[
+                ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,2
+                ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,3
+                ["h", "h", "d", "d", "d", "h", "h", "h", "h", "h"], //A,4
+                ["h", "h", "d", "d", "d", "h", "h", "h", "h", "h"],
+                ["h", "d", "d", "d", "d", "h", "h", "h", "h", "h"],
+                ["d", "d", "d", "d", "d", "s", "s", "h", "h", "h"],
+                ["s", "s", "s", "s", "d", "s", "s", "s", "s", "s"],
+                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"],
+                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //A,10 (soft 21)
+                ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,A
+            ]),
+            split: arr2(&[
+                ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"], // 2,2
+                ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"], // 3,3
+                ["n", "n", "n", "y", "y", "n", "n", "n", "n", "n"],
+                ["n", "n", "n", "n", "n", "n", "n", "n", "n", "n"],
+                ["y", "y", "y", "y", "y", "n", "n", "n", "n", "n"],
+                ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"],
+                ["y", "y", "y", "y", "y", "y", "y", "y", "y", "y"],
+                ["y", "y", "y", "y", "y", "n", "y", "y", "n", "n"],
+                ["n", "n", "n", "n", "n", "n", "n", "n", "n", "n"], // 10, 10
+                ["y", "y", "y", "y", "y", "y", "y", "y", "y", "y"], // A,A
+            ]),
+        }
+    }
 
-            if let Err(_) = wallet.place_bet(BET_UNIT) {
-                // println!("Balance too low (${})", wallet.balance);
-                running_balance.push(wallet.balance);
-                break 'play;
-            }
+    /// Basic strategy for a game with `rules`, doubling after splits as the
+    /// simulation always allows.
+    pub fn optimal(rules: &Rules) -> Self {
+        Strategy::optimal_for(rules, true)
+    }
+
+    /// Basic strategy as the Wizard of Odds publishes it for the number of
+    /// decks (four or more all play the same), whether the dealer hits soft 17
+    /// and whether doubling after a split is allowed.
+    pub fn optimal_for(rules: &Rules, double_after_split: bool) -> Self {
+        let single = rules.decks <= 1;
+        let double = rules.decks == 2;
+        let h17 = rules.dealer_hits_soft_17;
+
+        let mut hard = Array2::from_elem((HARD_CHART_ROWS, 10), "h");
+        if single {
+            mark(&mut hard, hard_row(8), 5..=6, "d");
+        }
+        mark(
+            &mut hard,
+            hard_row(9),
+            if single || double { 2..=6 } else { 3..=6 },
+            "d",
+        );
+        mark(&mut hard, hard_row(10), 2..=9, "d");
+        mark(&mut hard, hard_row(11), 2..=10, "d");
+        if h17 || single || double {
+            mark(&mut hard, hard_row(11), 11..=11, "d");
+        }
+        mark(&mut hard, hard_row(12), 4..=6, "s");
+        for total in 13..=18 {
+            mark(&mut hard, hard_row(total), 2..=6, "s");
+        }
+        for total in 17..=21 {
+            mark(&mut hard, hard_row(total), 7..=11, "s");
+        }
+        for total in 19..=21 {
+            mark(&mut hard, hard_row(total), 2..=6, "s");
+        }
+
+        let mut soft = Array2::from_elem((10, 10), "h");
+        let small = if single { 4..=6 } else { 5..=6 };
+        mark(&mut soft, soft_row(13), small.clone(), "d");
+        mark(&mut soft, soft_row(14), small, "d");
+        mark(&mut soft, soft_row(15), 4..=6, "d");
+        mark(&mut soft, soft_row(16), 4..=6, "d");
+        mark(
+            &mut soft,
+            soft_row(17),
+            if single { 2..=6 } else { 3..=6 },
+            "d",
+        );
+        mark(&mut soft, soft_row(18), 2..=8, "s");
+        mark(
+            &mut soft,
+            soft_row(18),
+            if h17 { 2..=6 } else { 3..=6 },
+            "d",
+        );
+        if single && !h17 {
+            mark(&mut soft, soft_row(18), 11..=11, "s");
+        }
+        for total in 19..=21 {
+            mark(&mut soft, soft_row(total), 2..=11, "s");
+        }
+        if h17 || single {
+            mark(&mut soft, soft_row(19), 6..=6, "d");
+        }
+
+        // Upcards to split 2s, 3s, 4s, 6s and 7s against
+        let (twos, threes, fours, sixes, sevens) = match (single, double, double_after_split) {
+            (true, _, true) => (2..=7, 2..=8, Some(4..=6), 2..=7, 2..=8),
+            (_, true, true) => (2..=7, 2..=7, Some(5..=6), 2..=7, 2..=8),
+            (_, _, true) => (2..=7, 2..=7, Some(5..=6), 2..=6, 2..=7),
+            (true, _, false) | (_, true, false) => (3..=7, 4..=7, None, 2..=6, 2..=7),
+            (_, _, false) => (4..=7, 4..=7, None, 3..=6, 2..=7),
+        };
+        let mut split = Array2::from_elem((10, 10), "n");
+        mark(&mut split, pair_row(2), twos, "y");
+        mark(&mut split, pair_row(3), threes, "y");
+        if let Some(fours) = fours {
+            mark(&mut split, pair_row(4), fours, "y");
+        }
+        mark(&mut split, pair_row(6), sixes, "y");
+        mark(&mut split, pair_row(7), sevens, "y");
+        mark(&mut split, pair_row(8), 2..=11, "y");
+        mark(&mut split, pair_row(9), 2..=6, "y");
+        mark(&mut split, pair_row(9), 8..=9, "y");
+        mark(&mut split, pair_row(11), 2..=11, "y");
+
+        Strategy { hard, soft, split }
+    }
+
+    /// The Wizard of Odds' simple strategy, which gives up a little on basic
+    /// strategy to be easier to remember: it always doubles 11 and doubles
+    /// soft 18 against 2 to 6.
+    pub fn wizard_simple() -> Self {
+        let mut strategy = Strategy::optimal(&Rules::default());
+        mark(&mut strategy.hard, hard_row(11), 2..=11, "d");
+        mark(&mut strategy.soft, soft_row(18), 2..=6, "d");
+        strategy
+    }
+
+    /// Plays like the dealer: hits below 17, stands on soft 17, and never
+    /// doubles or splits.
+    pub fn mimic_dealer() -> Self {
+        Strategy::stand_from(17, 17)
+    }
+
+    /// Only hits hands that can't bust, and soft hands below 18. Never
+    /// doubles or splits.
+    pub fn never_bust() -> Self {
+        Strategy::stand_from(12, 18)
+    }
+
+    /// Stands on every hand, even a pair of 2s.
+    pub fn always_stand() -> Self {
+        Strategy::stand_from(LOWEST_HARD, 12)
+    }
 
-            let mut hand = Hand::new(&mut deck);
+    /// Hits hard totals below `hard` and soft totals below `soft`, and stands
+    /// on the rest.
+    fn stand_from(hard: usize, soft: usize) -> Self {
+        let mut strategy = Strategy {
+            hard: Array2::from_elem((HARD_CHART_ROWS, 10), "h"),
+            soft: Array2::from_elem((10, 10), "h"),
+            split: Array2::from_elem((10, 10), "n"),
+        };
+        for total in hard..=21 {
+            mark(&mut strategy.hard, hard_row(total), 2..=11, "s");
+        }
+        for total in soft.max(12)..=21 {
+            mark(&mut strategy.soft, soft_row(total), 2..=11, "s");
+        }
+        strategy
+    }
+
+    /// What the matrices say to do with `cards` against the dealer's `upcard`.
+    fn advise(&self, cards: &Cards, upcard: Card, can_split: bool, can_double: bool) -> Action {
+        let dealer = to_index!(upcard);
+        let first = cards.cards[0];
+        if can_split
+            && cards.cards.len() >= 2
+            && first == cards.cards[1]
+            && self.split[[to_index!(first), dealer]] == "y"
+        {
+            return Action::Split;
+        }
+        let code = match cards.sum() {
+            // A lone card, left after a split, plays like the lowest total in
+            // its chart
+            Card::Maybe(_, n) => self.soft[[soft_row((n as usize).max(12)), dealer]],
+            Card::Def(n) => self.hard[[hard_row((n as usize).max(LOWEST_HARD)), dealer]],
+        };
+        match code {
+            "d" if can_double => Action::Double,
+            "s" => Action::Stand,
+            _ => Action::Hit,
+        }
+    }
+}
+
+/// Ready-made charts the Chooser can load in one click
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    /// Basic strategy from `Strategy::optimal_for`
+    Basic {
+        decks: usize,
+        dealer_hits_soft_17: bool,
+        double_after_split: bool,
+    },
+    WizardSimple,
+    MimicDealer,
+    NeverBust,
+    AlwaysStand,
+}
 
-            if hand.sum == Card::Maybe(11, 21) {
-                wallet.pay_out(3);
-                continue 'main;
+impl Preset {
+    /// Basic strategy for each common table, then the other charts
+    pub fn all() -> Vec<Preset> {
+        let mut presets = Vec::new();
+        for &decks in [1, 2, 6, 8].iter() {
+            for &dealer_hits_soft_17 in [false, true].iter() {
+                for &double_after_split in [true, false].iter() {
+                    presets.push(Preset::Basic {
+                        decks,
+                        dealer_hits_soft_17,
+                        double_after_split,
+                    });
+                }
             }
+        }
+        presets.extend_from_slice(&[
+            Preset::WizardSimple,
+            Preset::MimicDealer,
+            Preset::NeverBust,
+            Preset::AlwaysStand,
+        ]);
+        presets
+    }
+
+    pub fn strategy(self) -> Strategy {
+        match self {
+            Preset::Basic {
+                decks,
+                dealer_hits_soft_17,
+                double_after_split,
+            } => Strategy::optimal_for(
+                &Rules {
+                    decks,
+                    dealer_hits_soft_17,
+                    ..Rules::default()
+                },
+                double_after_split,
+            ),
+            Preset::WizardSimple => Strategy::wizard_simple(),
+            Preset::MimicDealer => Strategy::mimic_dealer(),
+            Preset::NeverBust => Strategy::never_bust(),
+            Preset::AlwaysStand => Strategy::always_stand(),
+        }
+    }
+
+    /// Sets the decks and soft 17 rule in `rules` to the ones a basic strategy
+    /// preset is for. The simulation always allows doubling after a split, so
+    /// that part of a preset only changes the chart.
+    pub fn apply_rules(self, rules: &mut Rules) {
+        if let Preset::Basic {
+            decks,
+            dealer_hits_soft_17,
+            ..
+        } = self
+        {
+            rules.decks = decks;
+            rules.dealer_hits_soft_17 = dealer_hits_soft_17;
+        }
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Preset::Basic {
+                decks,
+                dealer_hits_soft_17,
+                double_after_split,
+            } => write!(
+                f,
+                "Basic strategy: {} deck{}, {}, {}",
+                decks,
+                if *decks == 1 { "" } else { "s" },
+                if *dealer_hits_soft_17 { "H17" } else { "S17" },
+                if *double_after_split { "DAS" } else { "no DAS" }
+            ),
+            Preset::WizardSimple => "Wizard of Odds simple strategy".fmt(f),
+            Preset::MimicDealer => "Mimic the dealer".fmt(f),
+            Preset::NeverBust => "Never bust".fmt(f),
+            Preset::AlwaysStand => "Always stand".fmt(f),
+        }
+    }
+}
 
-            let mut dealer = Hand::new(&mut deck);
+/// Row of the hard chart for `total`
+fn hard_row(total: usize) -> usize {
+    total - LOWEST_HARD
+}
 
-            // --- Inputs ---
+/// Rows the hard chart had when it only covered 9 to 18
+const OLD_HARD_ROWS: usize = 10;
 
-            let mut split = false;
-            let mut bet = 0usize;
-            let mut hand2 = Hand {
-                cards: Cards { cards: vec![] },
-                busted: false,
-                sum: Card::Def(0),
+/// Fills out a hard chart from before it covered every total, which only had
+/// rows for 9 to 18, the way those charts were played: hitting 8 or less and
+/// standing on 19 or more. Other charts are left as they are.
+fn widen_hard(chart: Array2<&'static str>) -> Array2<&'static str> {
+    if chart.dim() != (OLD_HARD_ROWS, 10) {
+        return chart;
+    }
+    let mut wide = Array2::from_elem((HARD_CHART_ROWS, 10), "h");
+    for (i, row) in chart.outer_iter().enumerate() {
+        wide.row_mut(hard_row(9) + i).assign(&row);
+    }
+    for total in 19..=21 {
+        mark(&mut wide, hard_row(total), 2..=11, "s");
+    }
+    wide
+}
+
+/// Row of the soft chart for an ace and a card worth `total - 11`, with 12 for
+/// a pair of aces
+fn soft_row(total: usize) -> usize {
+    match total {
+        12 => 9,
+        n => n - 13,
+    }
+}
+
+/// Row of the split chart for a pair of `card`s, with 11 for aces
+fn pair_row(card: usize) -> usize {
+    card - 2
+}
+
+/// Sets `chart[row]` to `code` against each upcard in `upcards`, with 11 for
+/// an ace.
+fn mark(
+    chart: &mut Array2<&'static str>,
+    row: usize,
+    upcards: std::ops::RangeInclusive<usize>,
+    code: &'static str,
+) {
+    for upcard in upcards {
+        chart[[row, upcard - 2]] = code;
+    }
+}
+
+/// A decision the player can make about a hand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Hit,
+    Stand,
+    Double,
+    Split,
+    Surrender,
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Hit => "Hit",
+            Self::Stand => "Stand",
+            Self::Double => "Double",
+            Self::Split => "Split",
+            Self::Surrender => "Surrender",
+        }
+        .fmt(f)
+    }
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::Hit,
+        Action::Stand,
+        Action::Double,
+        Action::Split,
+        Action::Surrender,
+    ];
+
+    /// Keyboard shortcut for the action
+    pub fn key(self) -> char {
+        match self {
+            Self::Hit => 'h',
+            Self::Stand => 's',
+            Self::Double => 'd',
+            Self::Split => 'p',
+            Self::Surrender => 'r',
+        }
+    }
+
+    pub fn from_key(key: char) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|action| action.key() == key.to_ascii_lowercase())
+    }
+}
+
+/// Maps an action code onto the `'static` one used in the strategy matrices.
+pub fn action_code(code: &str) -> Option<&'static str> {
+    match code {
+        "s" => Some("s"),
+        "h" => Some("h"),
+        "d" => Some("d"),
+        "y" => Some("y"),
+        "n" => Some("n"),
+        _ => None,
+    }
+}
+
+/// (De)serializes a strategy matrix as rows of action codes, since borrowed
+/// `&'static str`s can't be deserialized directly.
+mod matrix {
+    use ndarray::Array2;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        array: &Array2<&'static str>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let rows: Vec<Vec<&str>> = array.outer_iter().map(|row| row.to_vec()).collect();
+        rows.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Array2<&'static str>, D::Error> {
+        let rows: Vec<Vec<String>> = Vec::deserialize(deserializer)?;
+        let shape = (rows.len(), rows.first().map_or(0, Vec::len));
+        let cells = rows
+            .iter()
+            .flatten()
+            .map(|code| {
+                super::action_code(code)
+                    .ok_or_else(|| D::Error::custom(format!("Unrecognisable entry {:?}", code)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Array2::from_shape_vec(shape, cells).map_err(D::Error::custom)
+    }
+
+    /// Reads a hard chart, filling out one saved before it covered every total.
+    pub fn deserialize_hard<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Array2<&'static str>, D::Error> {
+        deserialize(deserializer).map(super::widen_hard)
+    }
+}
+
+// --- CHART FILES ---
+
+/// Row labels of each chart in the CSV format, top to bottom
+const HARD_ROWS: [&str; HARD_CHART_ROWS] = [
+    "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15", "16", "17", "18", "19", "20",
+    "21",
+];
+const SOFT_ROWS: [&str; 10] = [
+    "A-2", "A-3", "A-4", "A-5", "A-6", "A-7", "A-8", "A-9", "A-10", "A-A",
+];
+const SPLIT_ROWS: [&str; 10] = [
+    "2-2", "3-3", "4-4", "5-5", "6-6", "7-7", "8-8", "9-9", "10-10", "A-A",
+];
+const CSV_HEADER: &str = "chart,hand,2,3,4,5,6,7,8,9,10,A";
+
+const LINK_VERSION: u8 = 2;
+/// Version of links made before the hard chart covered every total
+const OLD_LINK_VERSION: u8 = 1;
+/// Hard and soft codes by their two bit number in a link
+const LINK_CODES: [&str; 3] = ["h", "s", "d"];
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A strategy and the rules it was built for, for sharing charts between
+/// people and tools. There are two formats.
+///
+/// JSON is an object with a `rules` object (any field left out takes its
+/// default, and the whole object may be left out) and `hard`, `soft` and
+/// `split` charts. Each chart has rows of 10 action codes, one column per
+/// dealer upcard from 2 to 10 and then A:
+///
+/// ```text
+/// {
+///   "rules": { "decks": 6, "penetration": 0.75, "dealer_hits_soft_17": false, "surrender": true },
+///   "hard": [["h", "h", ...], ...],   18 rows for hard 4 to 21
+///   "soft": [["h", "h", ...], ...],   rows for A-2 to A-9, A-10 (soft 21), then A-A
+///   "split": [["y", "y", ...], ...]   rows for 2-2 to 10-10, then A-A
+/// }
+/// ```
+///
+/// Hard and soft charts use `h` (hit), `s` (stand) and `d` (double, or hit if
+/// you can't), and the split chart uses `y` and `n`. A plain `Strategy` file
+/// is a valid chart file with the default rules. Older files with a hard
+/// chart of 10 rows, for 9 to 18, still load, hitting 8 or less and standing
+/// on 19 or more.
+///
+/// CSV has a `rule,<name>,<value>` line per rule, then a
+/// `chart,hand,2,3,4,5,6,7,8,9,10,A` header and a line per chart row, such as
+/// `hard,16,s,s,s,s,s,h,h,h,h,h` or `split,A-A,y,y,y,y,y,y,y,y,y,y`. Blank
+/// lines and lines starting with `#` are skipped. Hard rows left out hit 8 or
+/// less and stand on 19 or more, and a left out soft A-10 stands.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartFile {
+    #[serde(default)]
+    pub rules: Rules,
+    #[serde(flatten)]
+    pub strategy: Strategy,
+}
+
+impl ChartFile {
+    /// Reads either format, going by whether the text starts with `{`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if text.trim_start().starts_with('{') {
+            ChartFile::from_json(text)
+        } else {
+            ChartFile::from_csv(text)
+        }
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let file: ChartFile = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        file.check()?;
+        Ok(file)
+    }
+
+    /// Writes one chart row per line, so the file reads like the chart.
+    pub fn to_json(&self) -> String {
+        let charts = [
+            ("hard", &self.strategy.hard),
+            ("soft", &self.strategy.soft),
+            ("split", &self.strategy.split),
+        ];
+        let charts: Vec<String> = charts
+            .iter()
+            .map(|(name, chart)| {
+                let rows: Vec<String> = chart
+                    .outer_iter()
+                    .map(|row| format!("    {}", serde_json::to_string(&row.to_vec()).unwrap()))
+                    .collect();
+                format!("  \"{}\": [\n{}\n  ]", name, rows.join(",\n"))
+            })
+            .collect();
+        format!(
+            "{{\n  \"rules\": {},\n{}\n}}\n",
+            serde_json::to_string(&self.rules).unwrap(),
+            charts.join(",\n")
+        )
+    }
+
+    pub fn from_csv(text: &str) -> Result<Self, String> {
+        let mut rules = serde_json::Map::new();
+        let mut charts: [Vec<Option<Vec<String>>>; 3] =
+            [vec![None; HARD_CHART_ROWS], vec![None; 10], vec![None; 10]];
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line == CSV_HEADER {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let at = |message: String| format!("Line {}: {}", number + 1, message);
+            let (labels, chart) = match fields[0] {
+                "rule" if fields.len() == 3 => {
+                    let value = serde_json::from_str(fields[2])
+                        .unwrap_or_else(|_| serde_json::Value::String(fields[2].to_string()));
+                    rules.insert(fields[1].to_string(), value);
+                    continue;
+                }
+                "hard" => (&HARD_ROWS[..], 0),
+                "soft" => (&SOFT_ROWS[..], 1),
+                "split" => (&SPLIT_ROWS[..], 2),
+                _ => return Err(at(format!("Unrecognisable line {:?}", line))),
             };
+            if fields.len() != 12 {
+                return Err(at("Expected a hand and 10 actions".to_string()));
+            }
+            let row = labels
+                .iter()
+                .position(|&label| label == fields[1])
+                .ok_or_else(|| at(format!("No {} row for {:?}", fields[0], fields[1])))?;
+            charts[chart][row] = Some(fields[2..].iter().map(|s| s.to_string()).collect());
+        }
 
-            macro_rules! player_input {
-                ( $e:expr ) => {{
-                    $e.show();
-                    if match $e.sum {
-                        Card::Def(n) => n > 21,
-                        _ => false,
-                    } {
-                        println!("Busted!");
-                        $e.busted = true;
-                        BUST_KWD
-                    } else {
-                        match &stdin().lock().lines().next().unwrap().unwrap()[..] {
-                            "s" => "s",
-                            "h" => "h",
-                            "d" => "d",
-                            "sp" => {
-                                if $e.cards.cards[0] == $e.cards.cards[1]
-                                    && wallet.balance >= wallet.bet
-                                    && bet == 0
-                                {
-                                    split = true;
-                                    $e.cards.cards.remove(1);
-                                    $e.sum = $e.cards.cards[0];
-                                    "h"
-                                } else {
-                                    "tortoise"
-                                }
-                            }
-                            "q" => break 'play,
-                            _ => "tortoise",
-                        }
-                    }
-                }};
+        // Published charts tend to leave out the hands that always hit or
+        // always stand, and A-10, which is a blackjack
+        for total in (LOWEST_HARD..=8).chain(19..=21) {
+            let row = &mut charts[0][hard_row(total)];
+            if row.is_none() {
+                *row = Some(vec![if total <= 8 { "h" } else { "s" }.to_string(); 10]);
             }
+        }
+        if charts[1][soft_row(21)].is_none() {
+            charts[1][soft_row(21)] = Some(vec!["s".to_string(); 10]);
+        }
+        let mut json = serde_json::Map::new();
+        for (chart, (name, labels)) in charts.iter().zip(
+            [
+                ("hard", &HARD_ROWS[..]),
+                ("soft", &SOFT_ROWS[..]),
+                ("split", &SPLIT_ROWS[..]),
+            ]
+            .iter(),
+        ) {
+            let rows = chart
+                .iter()
+                .zip(labels.iter())
+                .map(|(row, label)| {
+                    row.clone()
+                        .ok_or_else(|| format!("Missing the {} row for {}", name, label))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            json.insert(name.to_string(), rows.into());
+        }
+        json.insert("rules".to_string(), rules.into());
+        let file: ChartFile = serde_json::from_value(json.into()).map_err(|e| e.to_string())?;
+        file.check()?;
+        Ok(file)
+    }
 
-            macro_rules! dealer_input {
-                () => {{
-                    if real_sum!(dealer) < 17 {
-                        "h"
-                    } else {
-                        if match dealer.sum {
-                            Card::Def(n) => n > 21,
-                            _ => false,
-                        } {
-                            dealer.busted = true;
-                            BUST_KWD
-                        } else {
-                            "s"
-                        }
-                    }
-                }};
-            }
-
-            // let rule_split = arr2(&[
-            //     ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"], // 2,2
-            //     ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"], // 3,3
-            //     ["n", "n", "n", "y", "y", "n", "n", "n", "n", "n"],
-            //     ["n", "n", "n", "n", "n", "n", "n", "n", "n", "n"],
-            //     ["y", "y", "y", "y", "y", "n", "n", "n", "n", "n"],
-            //     ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"],
-            //     ["y", "y", "y", "y", "y", "y", "y", "y", "y", "y"],
-            //     ["y", "y", "y", "y", "y", "n", "y", "y", "n", "n"],
-            //     ["n", "n", "n", "n", "n", "n", "n", "n", "n", "n"], // 10, 10
-            //     ["y", "y", "y", "y", "y", "y", "y", "y", "y", "y"], // A,A
-            // ]);
-
-            // let rule_soft = arr2(&[
-            //     ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,2
-            //     ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,3
-            //     ["h", "h", "d", "d", "d", "h", "h", "h", "h", "h"], //A,4
-            //     ["h", "h", "d", "d", "d", "h", "h", "h", "h", "h"],
-            //     ["h", "d", "d", "d", "d", "h", "h", "h", "h", "h"],
-            //     ["d", "d", "d", "d", "d", "s", "s", "h", "h", "h"],
-            //     ["s", "s", "s", "s", "d", "s", "s", "s", "s", "s"],
-            //     ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"],
-            //     ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], // A,10
-            //     ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,A
-            // ]);
-
-            // let rule_hard = arr2(&[
-            //     ["s", "d", "d", "d", "d", "s", "s", "s", "s", "s"], // 9
-            //     ["d", "d", "d", "d", "d", "d", "d", "d", "s", "s"], // 10
-            //     ["d", "d", "d", "d", "d", "d", "d", "d", "d", "d"], // 11
-            //     ["h", "h", "s", "s", "s", "h", "h", "h", "h", "h"],
-            //     ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
-            //     ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
-            //     ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
-            //     ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"], //16
-            //     ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //17
-            //     ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //18
-            // ]);
-
-            // BACKUP
-            // hard_array: arr2(&[
-            //     ["s", "d", "d", "d", "d", "s", "s", "s", "s", "s"], // 9
-            //     ["d", "d", "d", "d", "d", "d", "d", "d", "s", "s"], // 10
-            //     ["d", "d", "d", "d", "d", "d", "d", "d", "d", "d"], // 11
-            //     ["h", "h", "s", "s", "s", "h", "h", "h", "h", "h"],
-            //     ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
-            //     ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
-            //     ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
-            //     ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"], //16
-            //     ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //17
-            //     ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //18
-            // ]),
-            // soft_array: arr2(&[
-            //     ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,2
-            //     ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,3
-            //     ["h", "h", "d", "d", "d", "h", "h", "h", "h", "h"], //A,4
-            //     ["h", "h", "d", "d", "d", "h", "h", "h", "h", "h"],
-            //     ["h", "d", "d", "d", "d", "h", "h", "h", "h", "h"],
-            //     ["d", "d", "d", "d", "d", "s", "s", "h", "h", "h"],
-            //     ["s", "s", "s", "s", "d", "s", "s", "s", "s", "s"],
-            //     ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"],
-            //     ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //A,10
-            //     ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,A
-            // ]),
-            // splits_array: arr2(&[
-            //     ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"], // 2,2
-            //     ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"], // 3,3
-            //     ["n", "n", "n", "y", "y", "n", "n", "n", "n", "n"],
-            //     ["n", "n", "n", "n", "n", "n", "n", "n", "n", "n"],
-            //     ["y", "y", "y", "y", "y", "n", "n", "n", "n", "n"],
-            //     ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"],
-            //     ["y", "y", "y", "y", "y", "y", "y", "y", "y", "y"],
-            //     ["y", "y", "y", "y", "y", "n", "y", "y", "n", "n"],
-            //     ["n", "n", "n", "n", "n", "n", "n", "n", "n", "n"], // 10, 10
-            //     ["y", "y", "y", "y", "y", "y", "y", "y", "y", "y"], // A,A
-            // ]),
-
-            macro_rules! basic_input {
-                ( $e:expr ) => {{
-                    let dealer_up = dealer.cards.cards[0];
-
-                    if match $e.sum {
-                        Card::Def(n) => n > 21,
-                        _ => false,
-                    } {
-                        hand.busted = true;
-                        BUST_KWD
-                    } else {
-                        if $e.cards.cards[0] == $e.cards.cards[1]
-                        && bet == 0
-                        && wallet.balance >= wallet.bet
-                        && rule_split[[to_index!($e.cards.cards[0]), to_index!(dealer_up)]] == "y"
-                        {
-                        split = true;
-                        $e.cards.cards.remove(1);
-                        $e.sum = $e.cards.cards[0];
-                        "h"
-                        } else {
-                            match $e.sum {
-                                Card::Maybe(_, 21) => "s",
-                                Card::Maybe(_, n) => {
-                                    rule_soft[[((match n {
-                                        12 => 9,
-                                        _ => n-13
-                                    })) as usize, to_index!(dealer_up)]]
-                                }
-                                Card::Def(n) => {
-                                    if n <= 8 { "h" } else {
-                                        if n >= 19 {"s"} else {
-                                            rule_hard[[(n-9) as usize, to_index!(dealer_up)]]
-                                        }
-                                    }
-                                },
-                            }
-                        }
-                    }
-                }};
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        if let serde_json::Value::Object(rules) = serde_json::to_value(self.rules).unwrap() {
+            for (name, value) in rules {
+                csv.push_str(&format!("rule,{},{}\n", name, value));
+            }
+        }
+        csv.push_str(CSV_HEADER);
+        csv.push('\n');
+        let charts = [
+            ("hard", &HARD_ROWS[..], &self.strategy.hard),
+            ("soft", &SOFT_ROWS[..], &self.strategy.soft),
+            ("split", &SPLIT_ROWS[..], &self.strategy.split),
+        ];
+        for (name, labels, chart) in charts.iter() {
+            for (label, row) in labels.iter().zip(chart.outer_iter()) {
+                csv.push_str(&format!("{},{},{}\n", name, label, row.to_vec().join(",")));
             }
+        }
+        csv
+    }
 
-            macro_rules! no_input {
-                () => {
-                    if real_sum!(hand) < 17 {
-                        "h"
-                    } else {
-                        if match hand.sum {
-                            Card::Def(n) => n > 21,
-                            _ => false,
-                        } {
-                            hand.busted = true;
-                            BUST_KWD
-                        } else {
-                            "s"
-                        }
-                    }
-                };
-            }
-
-            // --- Success Validation ---
-
-            macro_rules! win_lose {
-                ( $e:expr ) => {
-                    let hand_final = real_sum!($e);
-                    let dealer_final = real_sum!(dealer);
-                    if !$e.busted && (hand_final >= dealer_final || dealer.busted) {
-                        if hand_final == dealer_final {
-                            // println!("Push!");
-                            wallet.pay_out(1);
-                        } else {
-                            // println!("You Win!");
-                            wallet.pay_out(2);
-                        }
-                    } else {
-                        // println!("You Lose!");
-                        wallet.lose();
-                    }
-                };
+    /// Packs the chart into a short code for links. It's a version byte, the
+    /// number of decks, the penetration in percent and a byte of rule flags,
+    /// then two bits for each hard and soft cell and one for each split cell,
+    /// all in URL-safe base64. Links from before the hard chart covered every
+    /// total, with 10 hard rows, still open.
+    pub fn to_link_code(&self) -> String {
+        let mut bytes = vec![
+            LINK_VERSION,
+            self.rules.decks.min(255) as u8,
+            (self.rules.penetration * 100.).round() as u8,
+            self.rules.dealer_hits_soft_17 as u8 | (self.rules.surrender as u8) << 1,
+        ];
+        let mut bits = Vec::new();
+        for chart in [&self.strategy.hard, &self.strategy.soft].iter() {
+            for &code in chart.iter() {
+                let n = LINK_CODES.iter().position(|&c| c == code).unwrap_or(0);
+                bits.push(n & 2 != 0);
+                bits.push(n & 1 != 0);
+            }
+        }
+        bits.extend(self.strategy.split.iter().map(|&code| code == "y"));
+        bytes.extend(bits.chunks(8).map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, &bit)| byte | (bit as u8) << (7 - i))
+        }));
+        base64_encode(&bytes)
+    }
+
+    pub fn from_link_code(code: &str) -> Result<Self, String> {
+        let bytes = base64_decode(code).ok_or("That isn't a chart link")?;
+        let hard_rows = match bytes.first() {
+            Some(&LINK_VERSION) => HARD_CHART_ROWS,
+            Some(&OLD_LINK_VERSION) => OLD_HARD_ROWS,
+            _ => 0,
+        };
+        // Hard and soft cells take two bits each and split cells one
+        let split_offset = 2 * 10 * (hard_rows + 10);
+        if hard_rows == 0 || bytes.len() != 4 + (split_offset + 100).div_ceil(8) {
+            return Err("That chart link is from a different version".to_string());
+        }
+        let bit = |i: usize| bytes[4 + i / 8] & (0x80 >> (i % 8)) != 0;
+        let chart = |offset: usize, rows: usize| -> Result<Array2<&'static str>, String> {
+            let codes = (0..rows * 10)
+                .map(|i| {
+                    let n = (bit(offset + 2 * i) as usize) << 1 | bit(offset + 2 * i + 1) as usize;
+                    LINK_CODES.get(n).cloned()
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| "That chart link is damaged".to_string())?;
+            Ok(Array2::from_shape_vec((rows, 10), codes).unwrap())
+        };
+        let split = (0..100)
+            .map(|i| if bit(split_offset + i) { "y" } else { "n" })
+            .collect();
+        Ok(ChartFile {
+            rules: Rules {
+                decks: bytes[1] as usize,
+                penetration: bytes[2] as f64 / 100.,
+                dealer_hits_soft_17: bytes[3] & 1 != 0,
+                surrender: bytes[3] & 2 != 0,
+            },
+            strategy: Strategy {
+                hard: widen_hard(chart(0, hard_rows)?),
+                soft: chart(20 * hard_rows, 10)?,
+                split: Array2::from_shape_vec((10, 10), split).unwrap(),
+            },
+        })
+    }
+
+    /// Makes sure each chart is the right size and only uses the codes it can.
+    fn check(&self) -> Result<(), String> {
+        let charts = [
+            (
+                "hard",
+                &self.strategy.hard,
+                HARD_CHART_ROWS,
+                &["h", "s", "d"][..],
+            ),
+            ("soft", &self.strategy.soft, 10, &["h", "s", "d"]),
+            ("split", &self.strategy.split, 10, &["y", "n"]),
+        ];
+        for (name, chart, rows, codes) in charts.iter() {
+            if chart.dim() != (*rows, 10) {
+                return Err(format!("The {} chart should be {} rows of 10", name, rows));
             }
+            if let Some(code) = chart.iter().find(|&&code| !codes.contains(&code)) {
+                return Err(format!("{:?} can't be used in the {} chart", code, name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// URL-safe base64 without padding
+fn base64_encode(bytes: &[u8]) -> String {
+    bytes
+        .chunks(3)
+        .flat_map(|chunk| {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            (0..=chunk.len()).map(move |i| BASE64[(n >> (18 - 6 * i)) as usize & 63] as char)
+        })
+        .collect()
+}
 
-            // --- Play ---
-            while choice(dealer_input!(), &mut deck, &mut dealer, &mut wallet) {}
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let values = text
+        .bytes()
+        .map(|c| BASE64.iter().position(|&b| b == c).map(|v| v as u32))
+        .collect::<Option<Vec<_>>>()?;
+    let mut bytes = Vec::new();
+    for chunk in values.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |n, (i, &v)| n | v << (18 - 6 * i));
+        bytes.extend((0..chunk.len() - 1).map(|i| (n >> (16 - 8 * i)) as u8));
+    }
+    Some(bytes)
+}
 
-            while choice(basic_input!(hand), &mut deck, &mut hand, &mut wallet) {
-                if split {
-                    bet = wallet.bet;
-                    let val = hand.cards.cards[0] + Card::Def(0);
-                    hand2 = Hand {
-                        cards: Cards { cards: vec![val] },
-                        busted: false,
-                        sum: val,
-                    };
-                    hand2.hit(&mut deck);
+// --- BET SIZING ---
 
-                    // println!("--- HAND 1 ---");
-                    while choice(basic_input!(hand2), &mut deck, &mut hand2, &mut wallet) {}
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum BetSizing {
+    #[default]
+    Flat,
+    Ramp,
+    FullKelly,
+    HalfKelly,
+    QuarterKelly,
+}
 
-                    // println!("--- HAND 2 ---");
-                    split = false;
-                }
-            }
+impl std::fmt::Display for BetSizing {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Flat => "Flat",
+            Self::Ramp => "Ramp",
+            Self::FullKelly => "Full Kelly",
+            Self::HalfKelly => "Half Kelly",
+            Self::QuarterKelly => "Quarter Kelly",
+        }
+        .fmt(f)
+    }
+}
+
+impl BetSizing {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Flat => Self::Ramp,
+            Self::Ramp => Self::FullKelly,
+            Self::FullKelly => Self::HalfKelly,
+            Self::HalfKelly => Self::QuarterKelly,
+            Self::QuarterKelly => Self::Flat,
+        }
+    }
 
-            if bet != 0 {
-                win_lose!(hand2);
-                wallet.place_bet(bet).unwrap();
+    /// How much to wager given the current balance and Hi-Lo true count.
+    /// Never less than the table minimum of `BET_UNIT`.
+    fn amount(self, balance: usize, true_count: f64) -> usize {
+        let fraction = match self {
+            Self::Flat => return BET_UNIT,
+            Self::Ramp => {
+                return BET_UNIT
+                    * match true_count.floor() as isize {
+                        n if n <= 1 => 1,
+                        2 => 2,
+                        3 => 4,
+                        4 => 6,
+                        _ => 8,
+                    }
             }
+            Self::FullKelly => 1.,
+            Self::HalfKelly => 0.5,
+            Self::QuarterKelly => 0.25,
+        };
+        let advantage = BASE_EDGE + EDGE_PER_COUNT * true_count;
+        if advantage <= 0. {
+            return BET_UNIT;
+        }
+        let kelly = (balance as f64 * fraction * advantage / HAND_VARIANCE) as usize;
+        kelly.max(BET_UNIT)
+    }
+}
 
-            win_lose!(hand);
-            running_balance.push(wallet.balance);
+// --- HISTOGRAMS ---
+
+/// What happened in a single round
+struct Round {
+    /// Change in balance, in whole multiples of the starting bet
+    net_units: isize,
+    /// Exact change in balance as a multiple of the starting bet
+    result: f64,
+    /// Final total of each of the player's hands
+    player: Vec<u8>,
+    /// Dealer's final total, if they played
+    dealer: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Histograms {
+    /// Rounds by net result, from `-MAX_NET_UNITS` to `MAX_NET_UNITS` units
+    pub net_units: [usize; 17],
+    /// Hands by final total, with busts counted at index 22
+    pub player_totals: [usize; 23],
+    pub dealer_totals: [usize; 23],
+}
+
+impl Histograms {
+    fn record(&mut self, round: &Round) {
+        let net = round.net_units.clamp(-MAX_NET_UNITS, MAX_NET_UNITS);
+        self.net_units[(net + MAX_NET_UNITS) as usize] += 1;
+        for &total in round.player.iter() {
+            self.player_totals[total.min(22) as usize] += 1;
+        }
+        if let Some(total) = round.dealer {
+            self.dealer_totals[total.min(22) as usize] += 1;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn merge(&mut self, other: &Histograms) {
+        for (a, b) in self.net_units.iter_mut().zip(other.net_units.iter()) {
+            *a += b;
+        }
+        for (a, b) in self
+            .player_totals
+            .iter_mut()
+            .zip(other.player_totals.iter())
+        {
+            *a += b;
+        }
+        for (a, b) in self
+            .dealer_totals
+            .iter_mut()
+            .zip(other.dealer_totals.iter())
+        {
+            *a += b;
         }
+    }
+}
+
+// --- ESTIMATES ---
+
+/// z-score for a two-sided 95% confidence interval
+pub const CONFIDENCE_Z: f64 = 1.96;
+
+/// Running sums of each round's result, enough to estimate the mean and its
+/// standard error without keeping every round.
+#[derive(Debug, Clone, Copy, Default)]
+struct Tally {
+    rounds: usize,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl Tally {
+    fn record(&mut self, round: &Round) {
+        self.add(round.result);
+    }
 
-        // println!("Reshuffling cards...");
+    fn add(&mut self, result: f64) {
+        self.rounds += 1;
+        self.sum += result;
+        self.sum_sq += result * result;
     }
 
-    wallet.balance
+    #[cfg(not(target_arch = "wasm32"))]
+    fn merge(&mut self, other: &Tally) {
+        self.rounds += other.rounds;
+        self.sum += other.sum;
+        self.sum_sq += other.sum_sq;
+    }
+
+    fn estimate(&self) -> Estimate {
+        if self.rounds < 2 {
+            return Estimate {
+                rounds: self.rounds,
+                mean: self.sum,
+                std_error: f64::INFINITY,
+            };
+        }
+        let n = self.rounds as f64;
+        let mean = self.sum / n;
+        let variance = (self.sum_sq - n * mean * mean).max(0.) / (n - 1.);
+        Estimate {
+            rounds: self.rounds,
+            mean,
+            std_error: (variance / n).sqrt(),
+        }
+    }
+}
+
+/// Expected value per round, as a fraction of the starting bet
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Estimate {
+    pub rounds: usize,
+    pub mean: f64,
+    pub std_error: f64,
+}
+
+impl Estimate {
+    /// Half-width of the 95% confidence interval around `mean`
+    pub fn margin(&self) -> f64 {
+        CONFIDENCE_Z * self.std_error
+    }
+}
+
+// --- PROGRAM ---
+
+/// Spacing between samples so that `rounds` fits in about `TRAJECTORY_POINTS`.
+fn sample_step(rounds: usize) -> usize {
+    (rounds / TRAJECTORY_POINTS).max(1)
+}
+
+/// Round numbers to sample at: every `step`th round, always ending on `rounds`.
+fn sample_rounds(rounds: usize, step: usize) -> Vec<usize> {
+    let mut samples: Vec<usize> = (0..=rounds).step_by(step).collect();
+    if !rounds.is_multiple_of(step) {
+        samples.push(rounds);
+    }
+    samples
+}
+
+/// When a long run ends, other than by going broke
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Stopping {
+    /// After `NUM_LOOPS` shoes
+    #[default]
+    Shoes,
+    /// After exactly this many rounds, so runs can be compared like for like
+    Rounds(usize),
+    /// Once the standard error of the EV is at most `target` (a fraction of the
+    /// bet), or after `seconds` or `max_rounds`, whichever comes first. The
+    /// clock is kept by whoever drives the run.
+    Precision {
+        target: f64,
+        seconds: u32,
+        max_rounds: usize,
+    },
+}
+
+impl Stopping {
+    /// The most rounds the run can play, if it is capped
+    fn max_rounds(self) -> Option<usize> {
+        match self {
+            Stopping::Shoes => None,
+            Stopping::Rounds(rounds) => Some(rounds),
+            Stopping::Precision { max_rounds, .. } => Some(max_rounds),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Outcome {
+    pub balance: usize,
+    pub rounds: usize,
+    /// Seed of the shoe, which replays the run exactly with
+    /// `Simulation::with_seed`
+    pub seed: u64,
+    pub rules: Rules,
+    pub bet_sizing: BetSizing,
+    pub stopping: Stopping,
+    /// Whether the time budget ran out before the precision target was met
+    pub timed_out: bool,
+    /// Mean log growth of the bankroll per round
+    pub growth_rate: f64,
+    pub max_drawdown: usize,
+    /// Balance at rounds 0, `sample_every`, `2 * sample_every`, ... and at the
+    /// final round
+    pub trajectory: Vec<usize>,
+    pub sample_every: usize,
+    pub histograms: Histograms,
+    pub ev: Estimate,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Report {
+    Run(Outcome),
+    Sessions(SessionReport),
+    Comparison(ComparisonReport),
+}
+
+fn choice(input: &str, deck: &mut Deck, hand: &mut Hand, wallet: &mut Wallet) -> bool {
+    match input {
+        "s" => return false,
+        "h" => hand.hit(deck),
+        "d" => {
+            match wallet.double() {
+                Ok(_) => {
+                    hand.hit(deck);
+                    // hand.show();
+                    return false;
+                }
+                Err(_) => {
+                    // Can't cover the double, so just take the card
+                    hand.hit(deck);
+                }
+            };
+        }
+        BUST_KWD => return false,
+        _ => (),
+    }
+    true
+    // True means keep playing the round
+}
+
+/// Plays out a single round: places the bet, deals, plays the dealer's and
+/// player's hands and settles the wallet. Fails if the bet can't be covered.
+#[allow(unused_assignments)]
+fn play_round(
+    deck: &mut Deck,
+    wallet: &mut Wallet,
+    strategy: &Strategy,
+    rules: &Rules,
+    bet_sizing: BetSizing,
+) -> Result<Round, ()> {
+    let start = wallet.balance;
+    let amount = bet_sizing.amount(wallet.balance, deck.true_count());
+    wallet.place_bet(amount)?;
+
+    let mut hand = Hand::new(deck);
+
+    if hand.sum == Card::Maybe(11, 21) {
+        wallet.pay_out(3);
+        return Ok(Round {
+            net_units: (wallet.balance as isize - start as isize) / amount as isize,
+            result: (wallet.balance as f64 - start as f64) / amount as f64,
+            player: vec![21],
+            dealer: None,
+        });
+    }
+
+    let mut dealer = Hand::new(deck);
+
+    // --- Inputs ---
+
+    let mut split = false;
+    let mut bet = 0usize;
+    let mut hand2 = Hand {
+        cards: Cards { cards: vec![] },
+        busted: false,
+        sum: Card::Def(0),
+    };
+
+    macro_rules! dealer_input {
+        () => {{
+            if real_sum!(dealer) < 17
+                || (rules.dealer_hits_soft_17 && dealer.sum == Card::Maybe(7, 17))
+            {
+                "h"
+            } else {
+                if match dealer.sum {
+                    Card::Def(n) => n > 21,
+                    _ => false,
+                } {
+                    dealer.busted = true;
+                    BUST_KWD
+                } else {
+                    "s"
+                }
+            }
+        }};
+    }
+
+    macro_rules! basic_input {
+        ( $e:expr ) => {{
+            let dealer_up = dealer.cards.cards[0];
+
+            if match $e.sum {
+                Card::Def(n) => n > 21,
+                _ => false,
+            } {
+                $e.busted = true;
+                BUST_KWD
+            } else {
+                let can_split = bet == 0 && wallet.balance >= wallet.bet;
+                match strategy.advise(&$e.cards, dealer_up, can_split, true) {
+                    Action::Split => {
+                        split = true;
+                        $e.cards.cards.remove(1);
+                        $e.sum = $e.cards.cards[0];
+                        "h"
+                    }
+                    Action::Double => "d",
+                    Action::Hit => "h",
+                    Action::Stand | Action::Surrender => "s",
+                }
+            }
+        }};
+    }
+
+    // --- Success Validation ---
+
+    macro_rules! win_lose {
+        ( $e:expr ) => {
+            let hand_final = real_sum!($e);
+            let dealer_final = real_sum!(dealer);
+            if !$e.busted && (hand_final >= dealer_final || dealer.busted) {
+                if hand_final == dealer_final {
+                    // println!("Push!");
+                    wallet.pay_out(1);
+                } else {
+                    // println!("You Win!");
+                    wallet.pay_out(2);
+                }
+            } else {
+                // println!("You Lose!");
+                wallet.lose();
+            }
+        };
+    }
+
+    // --- Play ---
+    while choice(dealer_input!(), deck, &mut dealer, wallet) {}
+
+    while choice(basic_input!(hand), deck, &mut hand, wallet) {
+        if split {
+            // Hand 1's stake waits in `bet` while hand 2 plays for a stake of
+            // its own, which `basic_input!` only splits when the balance covers
+            bet = wallet.bet;
+            wallet.balance -= bet;
+            let val = hand.cards.cards[0] + Card::Def(0);
+            hand2 = Hand {
+                cards: Cards { cards: vec![val] },
+                busted: false,
+                sum: val,
+            };
+            hand2.hit(deck);
+
+            // println!("--- HAND 1 ---");
+            while choice(basic_input!(hand2), deck, &mut hand2, wallet) {}
+
+            win_lose!(hand2);
+            wallet.bet = bet;
+
+            // println!("--- HAND 2 ---");
+            split = false;
+        }
+    }
+
+    let mut player = vec![real_sum!(hand)];
+    if bet != 0 {
+        player.push(real_sum!(hand2));
+    }
+
+    win_lose!(hand);
+    Ok(Round {
+        net_units: (wallet.balance as isize - start as isize) / amount as isize,
+        result: (wallet.balance as f64 - start as f64) / amount as f64,
+        player,
+        dealer: Some(real_sum!(dealer)),
+    })
+}
+
+/// A single long run from `STARTING_MONEY`, which can be played a few shoes at
+/// a time.
+pub struct Simulation {
+    strategy: Strategy,
+    rules: Rules,
+    bet_sizing: BetSizing,
+    stopping: Stopping,
+    wallet: Wallet,
+    deck: Deck,
+    seed: u64,
+    running_balance: Vec<usize>,
+    histograms: Histograms,
+    tally: Tally,
+    shoes: usize,
+    broke: bool,
+    timed_out: bool,
+}
+
+impl Simulation {
+    pub fn new(
+        strategy: Strategy,
+        rules: Rules,
+        bet_sizing: BetSizing,
+        stopping: Stopping,
+    ) -> Self {
+        Simulation::with_seed(strategy, rules, bet_sizing, stopping, thread_rng().gen())
+    }
+
+    pub fn with_seed(
+        strategy: Strategy,
+        rules: Rules,
+        bet_sizing: BetSizing,
+        stopping: Stopping,
+        seed: u64,
+    ) -> Self {
+        Simulation {
+            strategy,
+            rules,
+            bet_sizing,
+            stopping,
+            wallet: Wallet::new(),
+            deck: Deck::with_seed(&rules, seed),
+            seed,
+            running_balance: vec![STARTING_MONEY],
+            histograms: Histograms::default(),
+            tally: Tally::default(),
+            shoes: 0,
+            broke: false,
+            timed_out: false,
+        }
+    }
+
+    /// Plays up to `shoes` more shoes. Returns whether the run is over.
+    pub fn step(&mut self, shoes: usize) -> bool {
+        for _ in 0..shoes {
+            if self.is_done() {
+                break;
+            }
+            self.deck.shuffle();
+
+            while !self.deck.needs_shuffle() {
+                if let Some(rounds) = self.stopping.max_rounds() {
+                    if self.running_balance.len() > rounds {
+                        break;
+                    }
+                }
+                match play_round(
+                    &mut self.deck,
+                    &mut self.wallet,
+                    &self.strategy,
+                    &self.rules,
+                    self.bet_sizing,
+                ) {
+                    Ok(round) => {
+                        self.histograms.record(&round);
+                        self.tally.record(&round);
+                    }
+                    Err(_) => {
+                        // println!("Balance too low (${})", wallet.balance);
+                        self.broke = true;
+                        break;
+                    }
+                }
+
+                self.running_balance.push(self.wallet.balance);
+            }
+
+            // println!("Reshuffling cards...");
+            self.shoes += 1;
+        }
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        if self.broke || self.timed_out {
+            return true;
+        }
+        match self.stopping {
+            Stopping::Shoes => self.shoes >= NUM_LOOPS,
+            Stopping::Rounds(rounds) => self.running_balance.len() > rounds,
+            Stopping::Precision {
+                target, max_rounds, ..
+            } => {
+                let ev = self.tally.estimate();
+                ev.rounds >= max_rounds
+                    || (ev.rounds >= MIN_PRECISION_ROUNDS && ev.std_error <= target)
+            }
+        }
+    }
+
+    pub fn stopping(&self) -> Stopping {
+        self.stopping
+    }
+
+    /// Ends a `Stopping::Precision` run where it is, once its time is up.
+    pub fn time_out(&mut self) {
+        self.timed_out = !self.is_done();
+    }
+
+    /// Fraction of the run played so far. For `Stopping::Precision` this is
+    /// how far the standard error has come towards the target, which shrinks
+    /// with the square root of the rounds played.
+    pub fn progress(&self) -> f64 {
+        if self.is_done() {
+            return 1.;
+        }
+        match self.stopping {
+            Stopping::Shoes => self.shoes as f64 / NUM_LOOPS as f64,
+            Stopping::Rounds(rounds) => {
+                (self.running_balance.len() - 1) as f64 / rounds.max(1) as f64
+            }
+            Stopping::Precision {
+                target, max_rounds, ..
+            } => {
+                let ev = self.tally.estimate();
+                let by_error = (target / ev.std_error).powi(2);
+                let by_rounds = ev.rounds as f64 / MIN_PRECISION_ROUNDS as f64;
+                let by_cap = ev.rounds as f64 / max_rounds.max(1) as f64;
+                by_error.min(by_rounds).max(by_cap).min(1.)
+            }
+        }
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        let mut peak = STARTING_MONEY;
+        let mut max_drawdown = 0;
+        for &balance in self.running_balance.iter() {
+            peak = peak.max(balance);
+            max_drawdown = max_drawdown.max(peak - balance);
+        }
+
+        let balance = self.wallet.balance;
+        let rounds = self.running_balance.len() - 1;
+        let sample_every = sample_step(rounds);
+        Outcome {
+            balance,
+            rounds,
+            seed: self.seed,
+            rules: self.rules,
+            bet_sizing: self.bet_sizing,
+            stopping: self.stopping,
+            timed_out: self.timed_out,
+            growth_rate: match (balance, rounds) {
+                (_, 0) => 0.,
+                (0, _) => f64::NEG_INFINITY,
+                (balance, _) => (balance as f64 / STARTING_MONEY as f64).ln() / rounds as f64,
+            },
+            max_drawdown,
+            trajectory: sample_rounds(rounds, sample_every)
+                .into_iter()
+                .map(|round| self.running_balance[round])
+                .collect(),
+            sample_every,
+            histograms: self.histograms.clone(),
+            ev: self.tally.estimate(),
+        }
+    }
+}
+
+/// Plays a whole run. A `Stopping::Precision` run stops at its target or
+/// `max_rounds`, as there's no clock here to enforce the time budget.
+pub fn play(
+    strategy: &Strategy,
+    rules: &Rules,
+    bet_sizing: BetSizing,
+    stopping: Stopping,
+) -> Outcome {
+    let mut simulation = Simulation::new(strategy.clone(), *rules, bet_sizing, stopping);
+    while !simulation.step(NUM_LOOPS) {}
+    simulation.outcome()
+}
+
+// --- SESSIONS ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SessionConfig {
+    pub sessions: usize,
+    pub rounds: usize,
+    pub bankroll: usize,
+    /// Profit at which the player walks away, or 0 for none
+    pub win_goal: usize,
+    /// Loss at which the player walks away, or 0 to play until ruin
+    pub stop_loss: usize,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            sessions: 1_000,
+            rounds: 500,
+            bankroll: 200,
+            win_goal: 100,
+            stop_loss: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionReport {
+    pub config: SessionConfig,
+    /// Seed of the shoe, which replays every session exactly with
+    /// `SessionSimulation::new`
+    pub seed: u64,
+    pub rules: Rules,
+    pub bet_sizing: BetSizing,
+    /// Fraction of sessions that ended unable to cover the next bet
+    pub risk_of_ruin: f64,
+    pub goal_probability: f64,
+    /// Final bankroll of every session, sorted ascending
+    pub final_bankrolls: Vec<usize>,
+    /// Largest peak-to-trough fall of every session, sorted ascending
+    pub max_drawdowns: Vec<usize>,
+    /// Bankroll percentiles (`BAND_PERCENTILES`) across sessions at rounds 0,
+    /// `sample_every`, `2 * sample_every`, ... and at the final round
+    pub bands: Vec<[usize; 5]>,
+    pub sample_every: usize,
+    /// Per-round histograms across every session
+    pub histograms: Histograms,
+    pub ev: Estimate,
+}
+
+pub const BAND_PERCENTILES: [f64; 5] = [5., 25., 50., 75., 95.];
+
+/// Value below which `p` percent of the (sorted) `values` fall.
+pub fn percentile(values: &[usize], p: f64) -> usize {
+    if values.is_empty() {
+        return 0;
+    }
+    let index = (p / 100. * (values.len() - 1) as f64).round() as usize;
+    values[index.min(values.len() - 1)]
+}
+
+/// `config.sessions` independent sessions of up to `config.rounds` rounds each,
+/// which can be played a few sessions at a time. A session stops early at the
+/// win goal, the stop-loss or ruin.
+pub struct SessionSimulation {
+    strategy: Strategy,
+    rules: Rules,
+    bet_sizing: BetSizing,
+    config: SessionConfig,
+    deck: Deck,
+    seed: u64,
+    goal: usize,
+    floor: usize,
+    samples: Vec<usize>,
+    sample_every: usize,
+    ruins: usize,
+    goals: usize,
+    final_bankrolls: Vec<usize>,
+    max_drawdowns: Vec<usize>,
+    paths: Vec<Vec<usize>>,
+    histograms: Histograms,
+    tally: Tally,
+}
+
+impl SessionSimulation {
+    pub fn new(
+        strategy: Strategy,
+        rules: Rules,
+        bet_sizing: BetSizing,
+        config: SessionConfig,
+        seed: u64,
+    ) -> Self {
+        let sample_every = sample_step(config.rounds);
+        SessionSimulation {
+            strategy,
+            rules,
+            bet_sizing,
+            config,
+            deck: Deck::with_seed(&rules, seed),
+            seed,
+            goal: match config.win_goal {
+                0 => usize::MAX,
+                win_goal => config.bankroll + win_goal,
+            },
+            floor: match config.stop_loss {
+                0 => 0,
+                stop_loss => config.bankroll.saturating_sub(stop_loss),
+            },
+            samples: sample_rounds(config.rounds, sample_every),
+            sample_every,
+            ruins: 0,
+            goals: 0,
+            final_bankrolls: Vec::with_capacity(config.sessions),
+            max_drawdowns: Vec::with_capacity(config.sessions),
+            paths: Vec::with_capacity(config.sessions),
+            histograms: Histograms::default(),
+            tally: Tally::default(),
+        }
+    }
+
+    /// Plays up to `sessions` more sessions. Returns whether all are done.
+    pub fn step(&mut self, sessions: usize) -> bool {
+        for _ in 0..sessions {
+            if self.is_done() {
+                break;
+            }
+            self.play_session();
+        }
+        self.is_done()
+    }
+
+    fn play_session(&mut self) {
+        let config = self.config;
+        let mut wallet = Wallet::with_balance(config.bankroll);
+        // Every session starts on a fresh shoe from the one seeded stream
+        let deck = &mut self.deck;
+        deck.shuffle();
+
+        let mut peak = config.bankroll;
+        let mut max_drawdown = 0;
+        let mut ruined = false;
+        let mut running_balance = vec![config.bankroll];
+
+        for _ in 0..config.rounds {
+            if deck.needs_shuffle() {
+                deck.shuffle();
+            }
+
+            match play_round(
+                deck,
+                &mut wallet,
+                &self.strategy,
+                &self.rules,
+                self.bet_sizing,
+            ) {
+                Ok(round) => {
+                    self.histograms.record(&round);
+                    self.tally.record(&round);
+                }
+                Err(_) => {
+                    ruined = true;
+                    break;
+                }
+            }
+
+            running_balance.push(wallet.balance);
+            peak = peak.max(wallet.balance);
+            max_drawdown = max_drawdown.max(peak - wallet.balance);
+
+            if wallet.balance >= self.goal {
+                self.goals += 1;
+                break;
+            }
+            if wallet.balance <= self.floor {
+                break;
+            }
+        }
+
+        if ruined || wallet.balance < BET_UNIT {
+            self.ruins += 1;
+        }
+        self.final_bankrolls.push(wallet.balance);
+        self.max_drawdowns.push(max_drawdown);
+        // Sessions that stopped early hold their final bankroll
+        self.paths.push(
+            self.samples
+                .iter()
+                .map(|&round| *running_balance.get(round).unwrap_or(&wallet.balance))
+                .collect(),
+        );
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.final_bankrolls.len() >= self.config.sessions
+    }
+
+    /// Fraction of the sessions played so far
+    pub fn progress(&self) -> f64 {
+        self.final_bankrolls.len() as f64 / self.config.sessions.max(1) as f64
+    }
+
+    pub fn report(&self) -> SessionReport {
+        let mut final_bankrolls = self.final_bankrolls.clone();
+        let mut max_drawdowns = self.max_drawdowns.clone();
+        final_bankrolls.sort();
+        max_drawdowns.sort();
+
+        let bands = (0..self.samples.len())
+            .map(|i| {
+                let mut column: Vec<usize> = self.paths.iter().map(|path| path[i]).collect();
+                column.sort();
+                let mut band = [0; 5];
+                for (value, &p) in band.iter_mut().zip(BAND_PERCENTILES.iter()) {
+                    *value = percentile(&column, p);
+                }
+                band
+            })
+            .collect();
+
+        let sessions = self.final_bankrolls.len().max(1) as f64;
+        SessionReport {
+            config: self.config,
+            seed: self.seed,
+            rules: self.rules,
+            bet_sizing: self.bet_sizing,
+            risk_of_ruin: self.ruins as f64 / sessions,
+            goal_probability: self.goals as f64 / sessions,
+            final_bankrolls,
+            max_drawdowns,
+            bands,
+            sample_every: self.sample_every,
+            histograms: self.histograms.clone(),
+            ev: self.tally.estimate(),
+        }
+    }
+}
+
+pub fn simulate_sessions(
+    strategy: &Strategy,
+    rules: &Rules,
+    bet_sizing: BetSizing,
+    config: SessionConfig,
+    seed: u64,
+) -> SessionReport {
+    let mut simulation = SessionSimulation::new(strategy.clone(), *rules, bet_sizing, config, seed);
+    simulation.step(config.sessions);
+    simulation.report()
+}
+
+// --- PARALLEL ---
+
+/// Totals from `play_parallel`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub rounds: usize,
+    pub threads: usize,
+    pub seed: u64,
+    pub rules: Rules,
+    pub bet_sizing: BetSizing,
+    /// Times a thread couldn't cover a bet and started again from
+    /// `STARTING_MONEY`
+    pub rebuys: usize,
+    pub ev: Estimate,
+    pub histograms: Histograms,
+}
+
+/// Seed for the `index`th of several independent streams started from `seed`.
+#[cfg(not(target_arch = "wasm32"))]
+fn stream_seed(seed: u64, index: usize) -> u64 {
+    seed.wrapping_add((index as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+/// `rounds` rounds with a deck seeded from `seed`, keeping only the totals so
+/// memory use doesn't grow with the number of rounds.
+#[cfg(not(target_arch = "wasm32"))]
+fn play_batch(
+    strategy: &Strategy,
+    rules: &Rules,
+    bet_sizing: BetSizing,
+    rounds: usize,
+    seed: u64,
+) -> (Tally, Histograms, usize) {
+    let mut tally = Tally::default();
+    let mut histograms = Histograms::default();
+    let mut rebuys = 0;
+    let mut wallet = Wallet::new();
+    let mut deck = Deck::with_seed(rules, seed);
+    deck.shuffle();
+
+    while tally.rounds < rounds {
+        if deck.needs_shuffle() {
+            deck.shuffle();
+        }
+        match play_round(&mut deck, &mut wallet, strategy, rules, bet_sizing) {
+            Ok(round) => {
+                histograms.record(&round);
+                tally.record(&round);
+            }
+            Err(_) => {
+                rebuys += 1;
+                wallet = Wallet::new();
+            }
+        }
+    }
+    (tally, histograms, rebuys)
+}
+
+/// Plays `rounds` rounds split across `threads` native threads, each with its
+/// own deck seeded from `seed`. The per-thread totals are merged in thread
+/// order, so the same seed and thread count always give the same report.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn play_parallel(
+    strategy: &Strategy,
+    rules: &Rules,
+    bet_sizing: BetSizing,
+    rounds: usize,
+    threads: usize,
+    seed: u64,
+) -> BatchReport {
+    let threads = threads.max(1);
+    let handles: Vec<_> = (0..threads)
+        .map(|i| {
+            let strategy = strategy.clone();
+            let rules = *rules;
+            let share = rounds / threads + if i < rounds % threads { 1 } else { 0 };
+            std::thread::spawn(move || {
+                play_batch(&strategy, &rules, bet_sizing, share, stream_seed(seed, i))
+            })
+        })
+        .collect();
+
+    let mut tally = Tally::default();
+    let mut histograms = Histograms::default();
+    let mut rebuys = 0;
+    for handle in handles {
+        let (batch_tally, batch_histograms, batch_rebuys) =
+            handle.join().expect("Simulation thread panicked");
+        tally.merge(&batch_tally);
+        histograms.merge(&batch_histograms);
+        rebuys += batch_rebuys;
+    }
+
+    BatchReport {
+        rounds: tally.rounds,
+        threads,
+        seed,
+        rules: *rules,
+        bet_sizing,
+        rebuys,
+        ev: tally.estimate(),
+        histograms,
+    }
+}
+
+// --- COMPARISON ---
+
+/// Results of `Comparison`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComparisonReport {
+    pub names: Vec<String>,
+    /// Rounds each strategy played
+    pub rounds: usize,
+    pub seed: u64,
+    pub rules: Rules,
+    pub bet_sizing: BetSizing,
+    /// Each strategy's EV per round
+    pub ev: Vec<Estimate>,
+    /// Each strategy's EV per round less the first one's, paired round by
+    /// round. The first is always zero.
+    pub difference: Vec<Estimate>,
+    /// Cells where the strategies don't all agree, with each one's chart
+    /// entry there
+    pub differing: Vec<(Cell, Vec<String>)>,
+}
+
+/// Plays several strategies head to head on common random numbers: every
+/// round starts from the same shoe for each of them, so they see the same
+/// cards and a difference in results comes from the strategies rather than
+/// the luck of the deal. That takes far fewer rounds to tell close strategies
+/// apart than running each one on its own.
+pub struct Comparison {
+    names: Vec<String>,
+    strategies: Vec<Strategy>,
+    rules: Rules,
+    bet_sizing: BetSizing,
+    rounds: usize,
+    seed: u64,
+    deck: Deck,
+    wallets: Vec<Wallet>,
+    tallies: Vec<Tally>,
+    differences: Vec<Tally>,
+}
+
+impl Comparison {
+    /// Sets up `rounds` rounds of each named strategy, the first of which the
+    /// others are measured against.
+    pub fn new(
+        entrants: Vec<(String, Strategy)>,
+        rules: Rules,
+        bet_sizing: BetSizing,
+        rounds: usize,
+        seed: u64,
+    ) -> Self {
+        let (names, strategies): (Vec<_>, Vec<_>) = entrants.into_iter().unzip();
+        let mut deck = Deck::with_seed(&rules, seed);
+        deck.shuffle();
+        Comparison {
+            wallets: strategies.iter().map(|_| Wallet::new()).collect(),
+            tallies: vec![Tally::default(); strategies.len()],
+            differences: vec![Tally::default(); strategies.len()],
+            names,
+            strategies,
+            rules,
+            bet_sizing,
+            rounds,
+            seed,
+            deck,
+        }
+    }
+
+    /// Plays up to `rounds` more rounds of every strategy. Returns whether the
+    /// comparison is over.
+    pub fn step(&mut self, rounds: usize) -> bool {
+        for _ in 0..rounds {
+            if self.is_done() {
+                break;
+            }
+            if self.deck.needs_shuffle() {
+                self.deck.shuffle();
+            }
+            let mut next = None;
+            let mut baseline = 0.;
+            for (i, strategy) in self.strategies.iter().enumerate() {
+                let mut deck = self.deck.clone();
+                let (wallet, rules, bet_sizing) =
+                    (&mut self.wallets[i], &self.rules, self.bet_sizing);
+                // A bet is placed before any cards are dealt, so going broke
+                // leaves the shoe as it was for the buy back. If even that
+                // can't cover the bet, the strategy sits the round out.
+                let result = play_round(&mut deck, wallet, strategy, rules, bet_sizing)
+                    .or_else(|_| {
+                        *wallet = Wallet::new();
+                        play_round(&mut deck, wallet, strategy, rules, bet_sizing)
+                    })
+                    .map_or(0., |round| round.result);
+                if i == 0 {
+                    baseline = result;
+                }
+                self.tallies[i].add(result);
+                self.differences[i].add(result - baseline);
+                // The shoe carries on as the first strategy left it
+                next.get_or_insert(deck);
+            }
+            if let Some(deck) = next {
+                self.deck = deck;
+            }
+        }
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.strategies.is_empty() || self.tallies[0].rounds >= self.rounds
+    }
+
+    /// Fraction of the rounds played so far
+    pub fn progress(&self) -> f64 {
+        match self.tallies.first() {
+            Some(tally) => tally.rounds as f64 / self.rounds.max(1) as f64,
+            None => 1.,
+        }
+    }
+
+    pub fn report(&self) -> ComparisonReport {
+        let differing = Cell::all()
+            .into_iter()
+            .filter_map(|cell| {
+                let codes: Vec<String> = self
+                    .strategies
+                    .iter()
+                    .map(|strategy| cell.code(strategy).to_string())
+                    .collect();
+                if codes.iter().all(|code| *code == codes[0]) {
+                    None
+                } else {
+                    Some((cell, codes))
+                }
+            })
+            .collect();
+        ComparisonReport {
+            names: self.names.clone(),
+            rounds: self.tallies.first().map_or(0, |tally| tally.rounds),
+            seed: self.seed,
+            rules: self.rules,
+            bet_sizing: self.bet_sizing,
+            ev: self.tallies.iter().map(Tally::estimate).collect(),
+            difference: self.differences.iter().map(Tally::estimate).collect(),
+            differing,
+        }
+    }
+}
+
+/// Plays `rounds` rounds of each strategy head to head, as in `Comparison`.
+pub fn compare(
+    entrants: Vec<(String, Strategy)>,
+    rules: &Rules,
+    bet_sizing: BetSizing,
+    rounds: usize,
+    seed: u64,
+) -> ComparisonReport {
+    let mut comparison = Comparison::new(entrants, *rules, bet_sizing, rounds, seed);
+    comparison.step(rounds);
+    comparison.report()
+}
+
+// --- TABLE ---
+
+/// How one of the player's hands was settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Settlement {
+    Blackjack,
+    Win,
+    Push,
+    Lose,
+    Surrendered,
+}
+
+impl std::fmt::Display for Settlement {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Blackjack => "Blackjack!",
+            Self::Win => "Win",
+            Self::Push => "Push",
+            Self::Lose => "Lose",
+            Self::Surrendered => "Surrendered",
+        }
+        .fmt(f)
+    }
+}
+
+/// Why a `Table` turned down a deal or an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableError {
+    /// A deal while the last round is still waiting on the player
+    RoundInProgress,
+    /// A deal with nothing bet
+    NoBet,
+    /// A bet bigger than the bankroll
+    CantCover(usize),
+    /// An action with no round going
+    NotYourTurn,
+    /// An action the current hand can't take
+    IllegalAction,
+}
+
+impl std::fmt::Display for TableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::RoundInProgress => write!(f, "Finish the round first"),
+            Self::NoBet => write!(f, "Add some chips first"),
+            Self::CantCover(bet) => write!(f, "You can't cover ${}", bet),
+            Self::NotYourTurn => write!(f, "There's no round going"),
+            Self::IllegalAction => write!(f, "You can't do that now"),
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+/// A hand as the player sees it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandView {
+    /// Card ranks, with "A" for aces and "?" for the dealer's hole card
+    pub cards: Vec<String>,
+    /// Best total of the visible cards
+    pub total: u8,
+    pub soft: bool,
+    pub bet: usize,
+    /// How the hand was settled and the net win or loss, once it has been
+    pub settlement: Option<(Settlement, isize)>,
+}
+
+fn card_label(card: Card) -> String {
+    match card {
+        Card::Maybe(_, _) => "A".to_string(),
+        Card::Def(n) => n.to_string(),
+    }
+}
+
+struct Seat {
+    hand: Hand,
+    bet: usize,
+    done: bool,
+    settlement: Option<(Settlement, isize)>,
+}
+
+impl Seat {
+    fn view(&self) -> HandView {
+        HandView {
+            cards: self
+                .hand
+                .cards
+                .cards
+                .iter()
+                .map(|&c| card_label(c))
+                .collect(),
+            total: real_sum!(self.hand),
+            soft: matches!(self.hand.sum, Card::Maybe(_, _)),
+            bet: self.bet,
+            settlement: self.settlement,
+        }
+    }
+
+    /// Pays back `returned` of the bet and closes the hand.
+    fn settle(&mut self, wallet: &mut Wallet, settlement: Settlement, returned: usize) {
+        wallet.credit(returned);
+        self.settlement = Some((settlement, returned as isize - self.bet as isize));
+        self.done = true;
+    }
+}
+
+/// A table where a person plays rounds one decision at a time, with the same
+/// deck, wallet and payouts as the simulations.
+pub struct Table {
+    rules: Rules,
+    deck: Deck,
+    wallet: Wallet,
+    dealer: Option<Hand>,
+    seats: Vec<Seat>,
+    active: usize,
+    split: bool,
+}
+
+impl Table {
+    pub fn new(rules: Rules, bankroll: usize) -> Self {
+        Table::with_deck(rules, bankroll, Deck::new(&rules))
+    }
+
+    pub fn with_seed(rules: Rules, bankroll: usize, seed: u64) -> Self {
+        Table::with_deck(rules, bankroll, Deck::with_seed(&rules, seed))
+    }
+
+    fn with_deck(rules: Rules, bankroll: usize, mut deck: Deck) -> Self {
+        deck.shuffle();
+        Table {
+            rules,
+            deck,
+            wallet: Wallet::with_balance(bankroll),
+            dealer: None,
+            seats: Vec::new(),
+            active: 0,
+            split: false,
+        }
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn balance(&self) -> usize {
+        self.wallet.balance
+    }
+
+    /// Whether a round is waiting on the player
+    pub fn in_round(&self) -> bool {
+        self.active < self.seats.len()
+    }
+
+    /// Starts a round with `bet` on a single hand. Fails if a round is already
+    /// going or the bet can't be covered.
+    pub fn deal(&mut self, bet: usize) -> Result<(), TableError> {
+        if self.in_round() {
+            return Err(TableError::RoundInProgress);
+        }
+        if bet == 0 {
+            return Err(TableError::NoBet);
+        }
+        self.wallet
+            .place_bet(bet)
+            .map_err(|_| TableError::CantCover(bet))?;
+        if self.deck.needs_shuffle() {
+            self.deck.shuffle();
+        }
+
+        let hand = Hand::new(&mut self.deck);
+        self.dealer = Some(Hand::new(&mut self.deck));
+        self.seats = vec![Seat {
+            hand,
+            bet,
+            done: false,
+            settlement: None,
+        }];
+        self.active = 0;
+        self.split = false;
+
+        // Naturals are paid straight away, as in the simulations
+        if self.seats[0].hand.sum == Card::Maybe(11, 21) {
+            self.seats[0].settle(&mut self.wallet, Settlement::Blackjack, 3 * bet);
+        }
+        self.advance();
+        Ok(())
+    }
+
+    /// What the player may do with the current hand
+    pub fn actions(&self) -> Vec<Action> {
+        let seat = match self.seats.get(self.active) {
+            Some(seat) => seat,
+            None => return Vec::new(),
+        };
+        let cards = &seat.hand.cards.cards;
+        let first_move = cards.len() == 2;
+        let covered = self.wallet.balance >= seat.bet;
+        Action::ALL
+            .iter()
+            .copied()
+            .filter(|action| match action {
+                Action::Hit | Action::Stand => true,
+                Action::Double => first_move && covered,
+                Action::Split => first_move && covered && !self.split && cards[0] == cards[1],
+                Action::Surrender => first_move && !self.split && self.rules.surrender,
+            })
+            .collect()
+    }
+
+    /// Plays `action` on the current hand. Fails if it isn't allowed right now.
+    pub fn act(&mut self, action: Action) -> Result<(), TableError> {
+        if !self.in_round() {
+            return Err(TableError::NotYourTurn);
+        }
+        if !self.actions().contains(&action) {
+            return Err(TableError::IllegalAction);
+        }
+        let seat = &mut self.seats[self.active];
+        match action {
+            Action::Hit => seat.hand.hit(&mut self.deck),
+            Action::Stand => seat.done = true,
+            Action::Double => {
+                self.wallet
+                    .place_bet(seat.bet)
+                    .map_err(|_| TableError::CantCover(seat.bet))?;
+                seat.bet *= 2;
+                seat.hand.hit(&mut self.deck);
+                seat.done = true;
+            }
+            Action::Split => {
+                self.wallet
+                    .place_bet(seat.bet)
+                    .map_err(|_| TableError::CantCover(seat.bet))?;
+                let card = seat.hand.cards.cards[1];
+                seat.hand = Hand::with_card(seat.hand.cards.cards[0]);
+                seat.hand.hit(&mut self.deck);
+                let mut hand = Hand::with_card(card);
+                hand.hit(&mut self.deck);
+                let bet = seat.bet;
+                self.seats.insert(
+                    self.active + 1,
+                    Seat {
+                        hand,
+                        bet,
+                        done: false,
+                        settlement: None,
+                    },
+                );
+                self.split = true;
+            }
+            Action::Surrender => {
+                let returned = seat.bet / 2;
+                seat.settle(&mut self.wallet, Settlement::Surrendered, returned);
+            }
+        }
+        self.advance();
+        Ok(())
+    }
+
+    /// What `strategy` would do with the current hand
+    pub fn hint(&self, strategy: &Strategy) -> Option<Action> {
+        let seat = self.seats.get(self.active)?;
+        let dealer = self.dealer.as_ref()?;
+        let actions = self.actions();
+        Some(strategy.advise(
+            &seat.hand.cards,
+            dealer.cards.cards[0],
+            actions.contains(&Action::Split),
+            actions.contains(&Action::Double),
+        ))
+    }
+
+    /// Hi-Lo running count of the cards the player has seen this shoe
+    pub fn running_count(&self) -> isize {
+        match (&self.dealer, self.in_round()) {
+            (Some(dealer), true) => self.deck.counter - hi_lo(dealer.cards.cards[1]),
+            _ => self.deck.counter,
+        }
+    }
+
+    /// `running_count` per deck left in the shoe
+    pub fn true_count(&self) -> f64 {
+        self.running_count() as f64 / (self.deck.drawables.len() as f64 / 52.)
+    }
+
+    /// Fraction of the shoe dealt since the last shuffle
+    pub fn penetration(&self) -> f64 {
+        1. - self.deck.drawables.len() as f64 / self.deck.cards.len() as f64
+    }
+
+    /// The player's hands this round, in the order they're played
+    pub fn hands(&self) -> Vec<HandView> {
+        self.seats.iter().map(Seat::view).collect()
+    }
+
+    /// Which of `hands` is being played, if the round is still going
+    pub fn active_hand(&self) -> Option<usize> {
+        if self.in_round() {
+            Some(self.active)
+        } else {
+            None
+        }
+    }
+
+    /// The dealer's hand, with the hole card hidden until the round is over
+    pub fn dealer(&self) -> Option<HandView> {
+        let dealer = self.dealer.as_ref()?;
+        if !self.in_round() {
+            return Some(HandView {
+                cards: dealer.cards.cards.iter().map(|&c| card_label(c)).collect(),
+                total: real_sum!(dealer),
+                soft: matches!(dealer.sum, Card::Maybe(_, _)),
+                bet: 0,
+                settlement: None,
+            });
+        }
+        let upcard = dealer.cards.cards[0];
+        Some(HandView {
+            cards: vec![card_label(upcard), "?".to_string()],
+            total: match upcard {
+                Card::Def(n) | Card::Maybe(_, n) => n,
+            },
+            soft: matches!(upcard, Card::Maybe(_, _)),
+            bet: 0,
+            settlement: None,
+        })
+    }
+
+    /// Moves on past finished hands, playing the dealer and settling up once
+    /// they're all done.
+    fn advance(&mut self) {
+        while let Some(seat) = self.seats.get_mut(self.active) {
+            if real_sum!(seat.hand) > 21 {
+                seat.hand.busted = true;
+                seat.settle(&mut self.wallet, Settlement::Lose, 0);
+            } else if real_sum!(seat.hand) == 21 {
+                seat.done = true;
+            }
+            if !seat.done {
+                return;
+            }
+            self.active += 1;
+        }
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        let dealer = match self.dealer.as_mut() {
+            Some(dealer) => dealer,
+            None => return,
+        };
+        if self.seats.iter().any(|seat| seat.settlement.is_none()) {
+            while real_sum!(dealer) < 17
+                || (self.rules.dealer_hits_soft_17 && dealer.sum == Card::Maybe(7, 17))
+            {
+                dealer.hit(&mut self.deck);
+            }
+        }
+        let dealer_final = real_sum!(dealer);
+        for seat in self
+            .seats
+            .iter_mut()
+            .filter(|seat| seat.settlement.is_none())
+        {
+            let hand_final = real_sum!(seat.hand);
+            let bet = seat.bet;
+            if dealer_final > 21 || hand_final > dealer_final {
+                seat.settle(&mut self.wallet, Settlement::Win, 2 * bet);
+            } else if hand_final == dealer_final {
+                seat.settle(&mut self.wallet, Settlement::Push, bet);
+            } else {
+                seat.settle(&mut self.wallet, Settlement::Lose, 0);
+            }
+        }
+        self.wallet.lose();
+    }
+}
+
+// --- TRAINER ---
+
+const MAX_DRILL_WEIGHT: f64 = 8.;
+
+/// Which of the Chooser's charts a decision is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Chart {
+    Hard,
+    Soft,
+    Split,
+}
+
+/// One decision in the Chooser's charts, as a row of a chart against the
+/// dealer's upcard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cell {
+    pub chart: Chart,
+    pub row: usize,
+    pub column: usize,
+}
+
+impl Cell {
+    /// Every cell that can be set in the Chooser
+    pub fn all() -> Vec<Cell> {
+        let mut cells = Vec::new();
+        for &chart in [Chart::Hard, Chart::Soft, Chart::Split].iter() {
+            let rows = match chart {
+                Chart::Hard => HARD_CHART_ROWS,
+                _ => 10,
+            };
+            for row in 0..rows {
+                for column in 0..10 {
+                    cells.push(Cell { chart, row, column });
+                }
+            }
+        }
+        cells
+    }
+
+    /// Whether a two card hand can land here. Hard 4, 20 and 21 can't without
+    /// a pair or an ace, and A,10 is a blackjack.
+    fn dealable(&self) -> bool {
+        match self.chart {
+            Chart::Hard => (5..=19).contains(&(self.row + LOWEST_HARD)),
+            Chart::Soft => self.row != soft_row(21),
+            Chart::Split => true,
+        }
+    }
+
+    /// What `strategy` does here, as a chart entry ("h", "s", "d", "y" or "n")
+    pub fn code(&self, strategy: &Strategy) -> &'static str {
+        let chart = match self.chart {
+            Chart::Hard => &strategy.hard,
+            Chart::Soft => &strategy.soft,
+            Chart::Split => &strategy.split,
+        };
+        chart[[self.row, self.column]]
+    }
+
+    /// The dealer's upcard, labelled like the cards in a `HandView`
+    pub fn upcard(&self) -> String {
+        rank_label(self.column)
+    }
+
+    /// Deals a two card hand that lands in this cell. Hard hands are never
+    /// pairs, so they can't be mistaken for a split decision.
+    fn deal(&self, rng: &mut StdRng) -> Vec<String> {
+        match self.chart {
+            Chart::Hard => {
+                let total = self.row + LOWEST_HARD;
+                let firsts: Vec<usize> = (2..=10)
+                    .filter(|&a| total > a && (2..=10).contains(&(total - a)) && 2 * a != total)
+                    .collect();
+                let first = *firsts.choose(rng).unwrap();
+                vec![first.to_string(), (total - first).to_string()]
+            }
+            Chart::Soft => vec!["A".to_string(), rank_label(self.row)],
+            Chart::Split => vec![rank_label(self.row), rank_label(self.row)],
+        }
+    }
+}
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.chart {
+            Chart::Hard => format!("Hard {} vs {}", self.row + LOWEST_HARD, self.upcard()),
+            Chart::Soft => format!(
+                "Soft {} vs {}",
+                if self.row == 9 { 12 } else { self.row + 13 },
+                self.upcard()
+            ),
+            Chart::Split => format!("Pair of {}s vs {}", rank_label(self.row), self.upcard()),
+        }
+        .fmt(f)
+    }
+}
+
+/// Labels a chart row or column (2 to 10, then A) like the cards in a `HandView`
+fn rank_label(index: usize) -> String {
+    match index {
+        9 => "A".to_string(),
+        _ => (index + 2).to_string(),
+    }
+}
+
+/// How often each cell comes up, so the ones you get wrong are drilled more
+/// and the ones you've learnt fade out. Capped so a few bad cells can't crowd
+/// out everything else.
+fn drill_weight((right, wrong): (usize, usize)) -> f64 {
+    ((1. + 2. * wrong as f64) / (1. + right as f64)).min(MAX_DRILL_WEIGHT)
+}
+
+/// Deals hands from the Chooser's charts to drill, keeping score for each cell.
+pub struct Trainer {
+    rng: StdRng,
+    cells: Vec<Cell>,
+    /// Right and wrong answers for each of `cells`
+    scores: Vec<(usize, usize)>,
+    current: usize,
+    cards: Vec<String>,
+}
+
+impl Default for Trainer {
+    fn default() -> Self {
+        Trainer::new()
+    }
+}
+
+impl Trainer {
+    pub fn new() -> Self {
+        Trainer::with_seed(thread_rng().gen())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        let cells: Vec<Cell> = Cell::all().into_iter().filter(Cell::dealable).collect();
+        let mut trainer = Trainer {
+            rng: StdRng::seed_from_u64(seed),
+            scores: vec![(0, 0); cells.len()],
+            cells,
+            current: 0,
+            cards: Vec::new(),
+        };
+        trainer.next();
+        trainer
+    }
+
+    /// The decision being asked about
+    pub fn cell(&self) -> Cell {
+        self.cells[self.current]
+    }
+
+    /// The player's cards for the current question
+    pub fn cards(&self) -> &[String] {
+        &self.cards
+    }
+
+    /// Marks `code` against what `strategy` does in the current cell, then
+    /// deals the next question. Returns whether it was right.
+    pub fn answer(&mut self, code: &str, strategy: &Strategy) -> bool {
+        let right = self.cell().code(strategy) == code;
+        let score = &mut self.scores[self.current];
+        if right {
+            score.0 += 1;
+        } else {
+            score.1 += 1;
+        }
+        self.next();
+        right
+    }
+
+    /// Right and wrong answers so far, over every cell
+    pub fn accuracy(&self) -> (usize, usize) {
+        self.scores
+            .iter()
+            .fold((0, 0), |(right, wrong), s| (right + s.0, wrong + s.1))
+    }
+
+    /// Right and wrong answers so far for `cell`
+    pub fn score(&self, cell: Cell) -> (usize, usize) {
+        self.cells
+            .iter()
+            .position(|&c| c == cell)
+            .map_or((0, 0), |i| self.scores[i])
+    }
+
+    /// Up to `n` cells with wrong answers, worst accuracy first
+    pub fn weakest(&self, n: usize) -> Vec<(Cell, (usize, usize))> {
+        let mut weakest: Vec<(Cell, (usize, usize))> = self
+            .cells
+            .iter()
+            .cloned()
+            .zip(self.scores.iter().cloned())
+            .filter(|&(_, (_, wrong))| wrong > 0)
+            .collect();
+        weakest.sort_by(|(_, a), (_, b)| {
+            let accuracy = |&(right, wrong): &(usize, usize)| right as f64 / (right + wrong) as f64;
+            accuracy(a)
+                .partial_cmp(&accuracy(b))
+                .unwrap()
+                .then(b.1.cmp(&a.1))
+        });
+        weakest.truncate(n);
+        weakest
+    }
+
+    /// Picks the next cell, never the same one twice in a row.
+    fn next(&mut self) {
+        let current = self.current;
+        let scores = &self.scores;
+        let asked = !self.cards.is_empty();
+        let indices: Vec<usize> = (0..self.cells.len()).collect();
+        self.current = *indices
+            .choose_weighted(&mut self.rng, |&i| {
+                if asked && i == current {
+                    0.
+                } else {
+                    drill_weight(scores[i])
+                }
+            })
+            .unwrap();
+        self.cards = self.cells[self.current].deal(&mut self.rng);
+    }
+}
+
+// --- COUNT DRILL ---
+
+/// How far off a true count answer can be and still count as right, since
+/// counters only estimate the decks left
+pub const TRUE_COUNT_TOLERANCE: f64 = 0.5;
+
+/// Flashes cards from a shoe for practising the Hi-Lo count. The count
+/// carries on from round to round until the shoe is reshuffled, as at a table.
+pub struct CountDrill {
+    deck: Deck,
+}
+
+impl CountDrill {
+    pub fn new(rules: &Rules) -> Self {
+        CountDrill::with_deck(Deck::new(rules))
+    }
+
+    pub fn with_seed(rules: &Rules, seed: u64) -> Self {
+        CountDrill::with_deck(Deck::with_seed(rules, seed))
+    }
+
+    fn with_deck(mut deck: Deck) -> Self {
+        deck.shuffle();
+        CountDrill { deck }
+    }
+
+    /// Gets the shoe ready for a round of `cards` cards, shuffling if it's
+    /// reached the cut card or wouldn't last the round. Returns whether it
+    /// shuffled, which resets the count.
+    pub fn start(&mut self, cards: usize) -> bool {
+        if self.deck.needs_shuffle() || self.deck.drawables.len() < cards {
+            self.deck.shuffle();
+            return true;
+        }
+        false
+    }
+
+    /// Draws the next `n` cards to flash, labelled like the cards in a
+    /// `HandView`.
+    pub fn flash(&mut self, n: usize) -> Vec<String> {
+        let n = n.min(self.deck.drawables.len());
+        (0..n).map(|_| card_label(self.deck.draw())).collect()
+    }
+
+    pub fn running_count(&self) -> isize {
+        self.deck.counter
+    }
+
+    pub fn true_count(&self) -> f64 {
+        self.deck.true_count()
+    }
+
+    /// Cards dealt since the last shuffle
+    pub fn seen(&self) -> usize {
+        self.deck.cards.len() - self.deck.drawables.len()
+    }
+
+    /// Marks a running count and true count answer, in that order.
+    pub fn check(&self, running_count: isize, true_count: f64) -> (bool, bool) {
+        (
+            running_count == self.running_count(),
+            (true_count - self.true_count()).abs() <= TRUE_COUNT_TOLERANCE,
+        )
+    }
+}
+
+// --- LEADERBOARD ---
+
+/// Rounds in a long run in the browser. It's the same for everyone so the
+/// results can go on the leaderboard.
+pub const LONG_RUN_ROUNDS: usize = 2_000_000;
+/// The most decks a replayed run can use
+const MAX_SCORED_DECKS: usize = 8;
+/// Cards that must be left behind the cut card in a replayed run, so the last
+/// round of a shoe can't run out
+const MIN_CARDS_BEHIND_CUT: f64 = 20.;
+
+/// A long run's result as it's kept on the leaderboard, with everything
+/// needed to replay it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub player: String,
+    /// Winnings over the run, in dollars
+    pub score: isize,
+    /// Rounds the run was set to play
+    pub rounds: usize,
+    pub rules: Rules,
+    pub bet_sizing: BetSizing,
+    pub seed: u64,
+    pub strategy: Strategy,
+    /// When the run finished, as an ISO 8601 date
+    pub date: String,
+}
+
+impl ScoreEntry {
+    /// The leaderboard entry for `outcome`, if it was a run of a set number
+    /// of rounds.
+    pub fn new(
+        outcome: &Outcome,
+        strategy: Strategy,
+        player: String,
+        date: String,
+    ) -> Option<Self> {
+        let rounds = match outcome.stopping {
+            Stopping::Rounds(rounds) => rounds,
+            _ => return None,
+        };
+        Some(ScoreEntry {
+            player,
+            score: outcome.balance as isize - STARTING_MONEY as isize,
+            rounds,
+            rules: outcome.rules,
+            bet_sizing: outcome.bet_sizing,
+            seed: outcome.seed,
+            strategy,
+            date,
+        })
+    }
+
+    /// Whether the two runs were played under the same rules and bet sizing
+    /// for the same number of rounds, so their scores mean the same thing.
+    pub fn comparable(&self, other: &ScoreEntry) -> bool {
+        self.rules == other.rules
+            && self.bet_sizing == other.bet_sizing
+            && self.rounds == other.rounds
+    }
+
+    /// Plays the run again from its seed and returns the score it really got.
+    pub fn replay(&self) -> isize {
+        let mut simulation = Simulation::with_seed(
+            self.strategy.clone(),
+            self.rules,
+            self.bet_sizing,
+            Stopping::Rounds(self.rounds),
+            self.seed,
+        );
+        while !simulation.step(NUM_LOOPS) {}
+        simulation.outcome().balance as isize - STARTING_MONEY as isize
+    }
+
+    /// Makes sure the run can be replayed in at most `max_rounds` rounds, then
+    /// replays it to check the score it claims.
+    pub fn verify(&self, max_rounds: usize) -> Result<(), String> {
+        if self.rounds == 0 || self.rounds > max_rounds {
+            return Err(format!("Runs must be 1 to {} rounds long", max_rounds));
+        }
+        if self.rules.decks == 0 || self.rules.decks > MAX_SCORED_DECKS {
+            return Err(format!("Runs must use 1 to {} decks", MAX_SCORED_DECKS));
+        }
+        let behind_cut = (52 * self.rules.decks) as f64 * (1. - self.rules.penetration);
+        if self.rules.penetration <= 0. || behind_cut < MIN_CARDS_BEHIND_CUT {
+            return Err("The penetration leaves too few cards behind the cut card".to_string());
+        }
+        ChartFile {
+            rules: self.rules,
+            strategy: self.strategy.clone(),
+        }
+        .check()?;
+        match self.replay() {
+            score if score == self.score => Ok(()),
+            score => Err(format!(
+                "Replaying the run scores {:+}, not {:+}",
+                score, self.score
+            )),
+        }
+    }
+}
+
+/// The entries comparable with `like`, best score first
+pub fn rank<'a>(entries: &'a [ScoreEntry], like: &ScoreEntry) -> Vec<&'a ScoreEntry> {
+    let mut ranked: Vec<&ScoreEntry> = entries.iter().filter(|e| e.comparable(like)).collect();
+    ranked.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_rules() -> Rules {
+        Rules {
+            decks: 2,
+            penetration: 0.5,
+            dealer_hits_soft_17: true,
+            surrender: false,
+        }
+    }
+
+    #[test]
+    fn play_parallel_repeats_with_the_same_seed() {
+        let strategy = Strategy::basic();
+        let rules = Rules::default();
+        let first = play_parallel(&strategy, &rules, BetSizing::Flat, 20_000, 3, 7);
+        let again = play_parallel(&strategy, &rules, BetSizing::Flat, 20_000, 3, 7);
+        assert_eq!(first, again);
+        assert_eq!(first.rounds, 20_000);
+
+        let other = play_parallel(&strategy, &rules, BetSizing::Flat, 20_000, 3, 8);
+        assert_ne!(first.ev, other.ev);
+    }
+
+    #[test]
+    fn session_simulation_repeats_with_the_same_seed() {
+        let config = SessionConfig {
+            sessions: 20,
+            rounds: 200,
+            ..SessionConfig::default()
+        };
+        let strategy = Strategy::basic();
+        let rules = Rules::default();
+        let first = simulate_sessions(&strategy, &rules, BetSizing::Flat, config, 11);
+        let again = simulate_sessions(&strategy, &rules, BetSizing::Flat, config, 11);
+        assert_eq!(first, again);
+        assert_eq!(first.seed, 11);
+    }
+
+    #[test]
+    fn precision_run_stops_at_its_round_cap() {
+        let stopping = Stopping::Precision {
+            target: 0.,
+            seconds: 0,
+            max_rounds: 5_000,
+        };
+        let outcome = play(
+            &Strategy::basic(),
+            &Rules::default(),
+            BetSizing::Flat,
+            stopping,
+        );
+        assert_eq!(outcome.rounds, 5_000);
+        assert!(!outcome.timed_out);
+    }
+
+    #[test]
+    fn comparison_deals_every_strategy_the_same_cards() {
+        let rules = Rules::default();
+        let basic = Strategy::basic();
+        let optimal = Strategy::optimal(&rules);
+        let entrants = |second: &Strategy| {
+            vec![
+                ("basic".to_string(), basic.clone()),
+                ("other".to_string(), second.clone()),
+            ]
+        };
+
+        let itself = compare(entrants(&basic), &rules, BetSizing::Flat, 20_000, 3);
+        assert_eq!(itself.ev[0], itself.ev[1]);
+        assert_eq!(itself.difference[1].mean, 0.);
+        assert_eq!(itself.difference[1].std_error, 0.);
+        assert!(itself.differing.is_empty());
+
+        // The shoe follows the first strategy, so what it sees doesn't depend
+        // on what it's up against
+        let against = compare(entrants(&optimal), &rules, BetSizing::Flat, 20_000, 3);
+        assert_eq!(against.ev[0], itself.ev[0]);
+        assert!(!against.differing.is_empty());
+    }
+
+    #[test]
+    fn chart_file_round_trips() {
+        let chart = ChartFile {
+            rules: small_rules(),
+            strategy: Strategy::optimal(&small_rules()),
+        };
+        assert_eq!(ChartFile::from_json(&chart.to_json()), Ok(chart.clone()));
+        assert_eq!(ChartFile::from_csv(&chart.to_csv()), Ok(chart.clone()));
+        assert_eq!(ChartFile::parse(&chart.to_csv()), Ok(chart.clone()));
+        assert_eq!(ChartFile::from_link_code(&chart.to_link_code()), Ok(chart));
+    }
+
+    #[test]
+    fn chart_file_rejects_malformed_input() {
+        assert!(ChartFile::from_json("{").is_err());
+        assert!(ChartFile::from_json(r#"{"hard": [["s"]], "soft": [], "split": []}"#).is_err());
+        assert!(ChartFile::from_csv("hard,16,s,s").is_err());
+        assert!(ChartFile::from_csv("rule,decks,many").is_err());
+        assert!(ChartFile::from_link_code("not a link!").is_err());
+        assert!(ChartFile::from_link_code("AAAA").is_err());
+
+        let mut chart = ChartFile {
+            rules: Rules::default(),
+            strategy: Strategy::basic(),
+        };
+        chart.strategy.split[[0, 0]] = "d";
+        assert!(ChartFile::from_json(&chart.to_json()).is_err());
+    }
+
+    /// A table part way through a round, with the player to act
+    fn table_in_round(rules: Rules) -> Table {
+        (0..)
+            .map(|seed| {
+                let mut table = Table::with_seed(rules, 100, seed);
+                table.deal(10).unwrap();
+                table
+            })
+            .find(Table::in_round)
+            .unwrap()
+    }
+
+    #[test]
+    fn table_turns_down_bad_deals() {
+        let mut table = Table::with_seed(Rules::default(), 100, 1);
+        assert_eq!(table.act(Action::Hit), Err(TableError::NotYourTurn));
+        assert_eq!(table.deal(0), Err(TableError::NoBet));
+        assert_eq!(table.deal(101), Err(TableError::CantCover(101)));
+        assert_eq!(table.balance(), 100);
+
+        let mut table = table_in_round(Rules::default());
+        assert_eq!(table.deal(10), Err(TableError::RoundInProgress));
+        assert_eq!(table.balance(), 90);
+    }
+
+    #[test]
+    fn table_plays_a_round_through() {
+        let mut table = table_in_round(small_rules());
+        assert_eq!(table.active_hand(), Some(0));
+        assert!(!table.actions().contains(&Action::Surrender));
+        assert_eq!(table.act(Action::Surrender), Err(TableError::IllegalAction));
+
+        while table.in_round() {
+            table.act(Action::Stand).unwrap();
+        }
+        assert_eq!(table.active_hand(), None);
+        assert_eq!(table.act(Action::Stand), Err(TableError::NotYourTurn));
+        let (settlement, net) = table.hands()[0].settlement.unwrap();
+        assert_eq!(table.balance() as isize, 100 + net);
+        assert_ne!(settlement, Settlement::Surrendered);
+        assert!(table.deal(10).is_ok());
+    }
+
+    #[test]
+    fn table_surrenders_half_the_bet() {
+        let mut table = table_in_round(Rules::default());
+        table.act(Action::Surrender).unwrap();
+        assert!(!table.in_round());
+        assert_eq!(
+            table.hands()[0].settlement,
+            Some((Settlement::Surrendered, -5))
+        );
+        assert_eq!(table.balance(), 95);
+    }
+
+    #[test]
+    fn drill_weight_favours_mistakes() {
+        assert_eq!(drill_weight((0, 0)), 1.);
+        assert!(drill_weight((0, 1)) > drill_weight((0, 0)));
+        assert!(drill_weight((3, 0)) < drill_weight((0, 0)));
+        assert!(drill_weight((1, 2)) > drill_weight((2, 1)));
+        assert_eq!(drill_weight((0, 100)), MAX_DRILL_WEIGHT);
+    }
+
+    #[test]
+    fn trainer_drills_missed_cells_more() {
+        let strategy = Strategy::basic();
+        let mut trainer = Trainer::with_seed(5);
+        let missed = trainer.cell();
+        for _ in 0..2_000 {
+            let cell = trainer.cell();
+            let code = if cell == missed {
+                "wrong"
+            } else {
+                cell.code(&strategy)
+            };
+            trainer.answer(code, &strategy);
+            assert_ne!(trainer.cell(), cell);
+        }
+
+        let (right, wrong) = trainer.score(missed);
+        assert_eq!(right, 0);
+        let (asked, _) = trainer.accuracy();
+        let cells = Cell::all().into_iter().filter(Cell::dealable).count();
+        // Far more than its share of the questions
+        assert!(wrong > 5 * (asked + wrong) / cells);
+        assert_eq!(trainer.weakest(1), vec![(missed, (0, wrong))]);
+    }
+
+    #[test]
+    fn score_entry_verifies_only_the_score_it_got() {
+        let strategy = Strategy::basic();
+        let simulation = |seed| {
+            let mut simulation = Simulation::with_seed(
+                strategy.clone(),
+                Rules::default(),
+                BetSizing::Ramp,
+                Stopping::Rounds(5_000),
+                seed,
+            );
+            while !simulation.step(NUM_LOOPS) {}
+            simulation.outcome()
+        };
+        let entry = ScoreEntry::new(
+            &simulation(21),
+            strategy.clone(),
+            "player".to_string(),
+            "2020-01-01".to_string(),
+        )
+        .unwrap();
+        assert_eq!(entry.verify(LONG_RUN_ROUNDS), Ok(()));
+        assert!(entry.verify(1_000).is_err());
+
+        let mut tampered = entry.clone();
+        tampered.score += 1;
+        assert!(tampered.verify(LONG_RUN_ROUNDS).is_err());
+
+        let mut tampered = entry.clone();
+        tampered.strategy = Strategy::optimal(&Rules::default());
+        assert!(tampered.verify(LONG_RUN_ROUNDS).is_err());
+
+        let mut tampered = entry;
+        tampered.seed += 1;
+        assert!(tampered.verify(LONG_RUN_ROUNDS).is_err());
+    }
 }
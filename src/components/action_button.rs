@@ -1,4 +1,5 @@
 use crate::components::chooser;
+use stdweb::web::event::{IMouseEvent, MouseButton};
 use yew::prelude::*;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -22,12 +23,21 @@ pub struct ActionButton {
     link: ComponentLink<Self>,
     onsignal: Callback<chooser::Msg>,
     active: bool,
+    onselect: Option<Callback<bool>>,
+    selected: bool,
+    cursor: bool,
+    id: String,
+    label: String,
+    role: &'static str,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Msg {
     CycleState,
     ButtonPressed,
+    /// The mouse went down on the button, or moved onto it while held down
+    Dragged(bool),
+    None,
 }
 
 #[derive(Properties, Clone, Debug, PartialEq)]
@@ -38,6 +48,16 @@ pub struct Props {
     pub onsignal: Callback<chooser::Msg>,
     #[props(required)]
     pub active: bool,
+    /// Called with `true` when a drag starts on the button, and `false` when
+    /// one moves onto it
+    pub onselect: Option<Callback<bool>>,
+    pub selected: bool,
+    /// Whether the keyboard is on this button
+    pub cursor: bool,
+    pub id: String,
+    /// What a screen reader says for the button
+    pub label: String,
+    pub role: &'static str,
 }
 
 impl Component for ActionButton {
@@ -50,6 +70,12 @@ impl Component for ActionButton {
             link,
             onsignal: props.onsignal,
             active: props.active,
+            onselect: props.onselect,
+            selected: props.selected,
+            cursor: props.cursor,
+            id: props.id,
+            label: props.label,
+            role: props.role,
         }
     }
 
@@ -68,6 +94,13 @@ impl Component for ActionButton {
             Msg::ButtonPressed => {
                 self.onsignal.emit(chooser::Msg::None);
             }
+            Msg::Dragged(start) => {
+                if let Some(onselect) = &self.onselect {
+                    onselect.emit(start);
+                }
+                return false;
+            }
+            Msg::None => return false,
         }
         true
     }
@@ -76,6 +109,12 @@ impl Component for ActionButton {
         self.state = props.state;
         self.active = props.active;
         self.onsignal = props.onsignal;
+        self.onselect = props.onselect;
+        self.selected = props.selected;
+        self.cursor = props.cursor;
+        self.id = props.id;
+        self.label = props.label;
+        self.role = props.role;
         true
     }
 
@@ -91,7 +130,18 @@ impl Component for ActionButton {
                         State::Y => "green",
                         State::N => "red",
                     }
-                }), onclick=self.link.callback(|_| Msg::ButtonPressed),>{ match self.state {
+                }, self.action_class(), if self.selected {"selected"} else {""}, if self.cursor {"cursor"} else {""}),
+                id=self.id.clone(), role=self.role, aria-label=self.label.clone(), aria-selected=self.selected.to_string(), tabindex="-1",
+                onclick=self.link.callback(|_| Msg::ButtonPressed),
+                onmousedown=self.link.callback(|e: MouseDownEvent| match e.button() {
+                    MouseButton::Left => Msg::Dragged(true),
+                    _ => Msg::None,
+                }),
+                onmouseover=self.link.callback(|e: MouseOverEvent| if e.buttons().is_down(MouseButton::Left) {
+                    Msg::Dragged(false)
+                } else {
+                    Msg::None
+                }),>{ match self.state {
                     State::T(99) | State::T(1) => "A".to_string(),
                     State::T(i) => format!("{}", i),
                     i => format!("{:?}", i),
@@ -108,7 +158,8 @@ impl Component for ActionButton {
                         State::Y => "green",
                         State::N => "red",
                     }
-                }),>{ match self.state {
+                }, self.action_class(), if self.cursor {"cursor"} else {""}),
+                id=self.id.clone(), role=self.role, aria-label=self.label.clone(), aria-disabled="true", tabindex="-1",>{ match self.state {
                     State::T(99) | State::T(1) => "A".to_string(),
                     State::T(i) => format!("{}", i),
                     i => format!("{:?}", i),
@@ -117,3 +168,17 @@ impl Component for ActionButton {
         }
     }
 }
+
+impl ActionButton {
+    /// Picks the button's color out of the colorblind-safe palette
+    fn action_class(&self) -> &'static str {
+        match self.state {
+            State::S => "action-s",
+            State::H => "action-h",
+            State::D => "action-d",
+            State::T(_) => "action-t",
+            State::Y => "action-y",
+            State::N => "action-n",
+        }
+    }
+}
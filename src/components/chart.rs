@@ -0,0 +1,106 @@
+use yew::prelude::*;
+
+const WIDTH: f64 = 400.;
+const HEIGHT: f64 = 200.;
+const PADDING: f64 = 10.;
+
+pub struct Chart {
+    title: String,
+    line: Vec<usize>,
+    bands: Vec<(Vec<usize>, Vec<usize>)>,
+    sample_every: usize,
+    rounds: usize,
+}
+
+pub enum Msg {}
+
+#[derive(PartialEq, Clone, Properties)]
+pub struct Props {
+    #[props(required)]
+    pub title: String,
+    /// Values sampled every `sample_every` rounds, the last one at `rounds`
+    #[props(required)]
+    pub line: Vec<usize>,
+    /// (lower, upper) pairs shaded behind the line, outermost first
+    pub bands: Vec<(Vec<usize>, Vec<usize>)>,
+    #[props(required)]
+    pub sample_every: usize,
+    #[props(required)]
+    pub rounds: usize,
+}
+
+impl Component for Chart {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Chart {
+            title: props.title,
+            line: props.line,
+            bands: props.bands,
+            sample_every: props.sample_every,
+            rounds: props.rounds,
+        }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.title = props.title;
+        self.line = props.line;
+        self.bands = props.bands;
+        self.sample_every = props.sample_every;
+        self.rounds = props.rounds;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let values = self
+            .line
+            .iter()
+            .chain(self.bands.iter().flat_map(|(lo, hi)| lo.iter().chain(hi)));
+        let (min, max) = values.fold((std::usize::MAX, 0), |(min, max), &v| {
+            (min.min(v), max.max(v))
+        });
+        if min > max {
+            return html! { <span /> };
+        }
+        let range = (max - min).max(1) as f64;
+        let rounds = self.rounds.max(1) as f64;
+
+        let point = |i: usize, value: usize| {
+            let round = (i * self.sample_every).min(self.rounds) as f64;
+            let x = PADDING + round / rounds * (WIDTH - 2. * PADDING);
+            let y = HEIGHT - PADDING - (value - min) as f64 / range * (HEIGHT - 2. * PADDING);
+            format!("{:.1},{:.1}", x, y)
+        };
+        let points = |values: &[usize]| {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| point(i, v))
+                .collect::<Vec<_>>()
+        };
+
+        html! {
+            <div class=("container", "chart"),>
+                <span class="chart-title",>{ &self.title }</span>
+                <svg class="chart-svg", viewBox=format!("0 0 {} {}", WIDTH, HEIGHT),>
+                    { for self.bands.iter().enumerate().map(|(i, (lo, hi))| {
+                        let mut outline = points(hi);
+                        outline.extend(points(lo).into_iter().rev());
+                        html! {
+                            <polygon class=format!("chart-band chart-band-{}", i), points=outline.join(" "),/>
+                        }
+                    })}
+                    <polyline class="chart-line", points=points(&self.line).join(" "),/>
+                    <text class="chart-label", x=PADDING, y=PADDING,>{ format!("${}", max) }</text>
+                    <text class="chart-label", x=PADDING, y=(HEIGHT - PADDING),>{ format!("${}", min) }</text>
+                    <text class="chart-label", x=(WIDTH - PADDING), y=(HEIGHT - PADDING), text-anchor="end",>{ format!("{} rounds", self.rounds) }</text>
+                </svg>
+            </div>
+        }
+    }
+}
@@ -1,13 +1,26 @@
-use crate::blackjack;
 use crate::components::{action_button, action_button::ActionButton, messages::Messages};
-use ndarray::{arr2, Array2};
+use crate::{blackjack, simulator, storage};
+use ndarray::Array2;
+use stdweb::web::event::{IEvent, IKeyboardEvent};
+use stdweb::web::window;
 use yew::prelude::*;
 
+/// Starts the fragment of a link that shares a chart
+const LINK_PREFIX: &str = "#chart=";
+/// Name the working chart goes by in a head to head
+const WORKING_NAME: &str = "Your chart";
+/// Most edits that can be undone
+const HISTORY_LIMIT: usize = 100;
+/// Shown when R is pressed on the grid
+const SURRENDER_NOTICE: &str =
+    "Charts can't surrender, as the simulations play every hand out. You can surrender at the table when the rules allow it.";
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum State {
     Hard,
     Soft,
     Splits,
+    Settings,
     None,
 }
 
@@ -17,6 +30,23 @@ impl Default for State {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunMode {
+    LongRun,
+    Precision,
+    Sessions,
+    Compare,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionField {
+    Sessions,
+    Rounds,
+    Bankroll,
+    WinGoal,
+    StopLoss,
+}
+
 #[derive(Properties, Clone, Debug, PartialEq)]
 pub struct Props {
     #[props(required)]
@@ -27,6 +57,45 @@ pub struct Props {
     pub on_end_game: Callback<crate::Msg>,
 }
 
+/// A rectangle of cells picked out by dragging across the grid
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Selection {
+    /// The cell the drag started on
+    anchor: (usize, usize),
+    /// The cell the drag is over now
+    corner: (usize, usize),
+}
+
+impl Selection {
+    fn rows(&self) -> std::ops::RangeInclusive<usize> {
+        self.anchor.0.min(self.corner.0)..=self.anchor.0.max(self.corner.0)
+    }
+
+    fn columns(&self) -> std::ops::RangeInclusive<usize> {
+        self.anchor.1.min(self.corner.1)..=self.anchor.1.max(self.corner.1)
+    }
+
+    fn contains(&self, i: usize, j: usize) -> bool {
+        self.rows().contains(&i) && self.columns().contains(&j)
+    }
+
+    fn cells(&self) -> Vec<(usize, usize)> {
+        let columns = self.columns();
+        self.rows()
+            .flat_map(|i| columns.clone().map(move |j| (i, j)))
+            .collect()
+    }
+}
+
+/// The panel for copying a chart out or pasting one in
+struct Transfer {
+    /// Whether it's showing a share link rather than a chart file
+    link: bool,
+    csv: bool,
+    text: String,
+    message: String,
+}
+
 pub struct Chooser {
     state: State,
     link: ComponentLink<Self>,
@@ -34,15 +103,75 @@ pub struct Chooser {
     hard_array: Array2<&'static str>,
     soft_array: Array2<&'static str>,
     splits_array: Array2<&'static str>,
+    bet_sizing: blackjack::BetSizing,
+    run_mode: RunMode,
+    session_config: blackjack::SessionConfig,
+    /// Standard error to stop at in `RunMode::Precision`, as a percentage of the bet
+    precision_target: f64,
+    time_budget: u32,
+    /// Rounds each strategy plays in `RunMode::Compare`
+    compare_rounds: usize,
+    /// Saved charts to play head to head against the working chart
+    pinned: Vec<String>,
+    strategies: storage::Strategies,
+    /// Name to save the working chart as
+    strategy_name: String,
+    /// The rules the chart was built for, which come with an imported or saved
+    /// chart or a preset
+    rules: blackjack::Rules,
+    transfer: Option<Transfer>,
+    selection: Option<Selection>,
+    /// The cell the keyboard is on
+    cursor: (usize, usize),
+    /// Why the last key on the grid did nothing, until the next change
+    notice: Option<&'static str>,
+    /// Charts as they were before each edit, most recent last
+    undo: Vec<blackjack::ChartFile>,
+    /// Edits that were undone, most recently undone last
+    redo: Vec<blackjack::ChartFile>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Msg {
     ChangeState(State),
-    ToggleRow(usize),
-    ToggleColumn(usize),
     ToggleCell(usize, usize),
+    SelectRow(usize),
+    SelectColumn(usize),
+    StartSelection(usize, usize),
+    ExtendSelection(usize, usize),
+    ClearSelection,
+    /// Moves the keyboard cursor by rows and columns, growing the selection if
+    /// the flag is set
+    MoveCursor(isize, isize, bool),
+    ToggleCursor,
+    SetSelection(&'static str),
+    Notice(&'static str),
+    ResetToPreset(usize),
+    Undo,
+    Redo,
+    TogglePalette,
+    CycleBetSizing,
+    CycleRunMode,
+    SetSessionField(SessionField, String),
+    SetPrecisionTarget(String),
+    SetTimeBudget(String),
+    SetCompareRounds(String),
+    TogglePin(String),
+    SetStrategyName(String),
+    SaveStrategy,
+    LoadStrategy(String),
+    DuplicateStrategy(String),
+    DeleteStrategy(String),
+    LoadPreset(usize),
+    Export,
+    Import,
+    Share,
+    SetTransferText(String),
+    ToggleTransferFormat,
+    ApplyImport,
+    CloseTransfer,
     PlayGame,
+    Train,
     None,
 }
 
@@ -52,46 +181,36 @@ impl Component for Chooser {
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         props.onsignal.emit(link.clone());
+        let mut strategies = storage::Strategies::open();
+        if let Some(chart) = linked_chart() {
+            strategies.autosave(chart);
+            // So coming back to the Chooser doesn't undo any edits
+            let _ = window().history().replace_state((), "", Some(&page_url()));
+        }
+        let working = strategies.library.working.clone();
         Self {
             state: props.state,
             link,
             on_end_game: props.on_end_game,
-            hard_array: arr2(&[
-                ["s", "d", "d", "d", "d", "s", "s", "s", "s", "s"], // 9
-                ["d", "d", "d", "d", "d", "d", "d", "d", "s", "s"], // 10
-                ["d", "d", "d", "d", "d", "d", "d", "d", "d", "d"], // 11
-                ["h", "h", "s", "s", "s", "h", "h", "h", "h", "h"],
-                ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
-                ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
-                ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"],
-                ["s", "s", "s", "s", "s", "h", "h", "h", "h", "h"], //16
-                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //17
-                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //18
-            ]),
-            soft_array: arr2(&[
-                ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,2
-                ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,3
-                ["h", "h", "d", "d", "d", "h", "h", "h", "h", "h"], //A,4
-                ["h", "h", "d", "d", "d", "h", "h", "h", "h", "h"],
-                ["h", "d", "d", "d", "d", "h", "h", "h", "h", "h"],
-                ["d", "d", "d", "d", "d", "s", "s", "h", "h", "h"],
-                ["s", "s", "s", "s", "d", "s", "s", "s", "s", "s"],
-                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"],
-                ["s", "s", "s", "s", "s", "s", "s", "s", "s", "s"], //A,10
-                ["h", "h", "h", "d", "d", "h", "h", "h", "h", "h"], //A,A
-            ]),
-            splits_array: arr2(&[
-                ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"], // 2,2
-                ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"], // 3,3
-                ["n", "n", "n", "y", "y", "n", "n", "n", "n", "n"],
-                ["n", "n", "n", "n", "n", "n", "n", "n", "n", "n"],
-                ["y", "y", "y", "y", "y", "n", "n", "n", "n", "n"],
-                ["y", "y", "y", "y", "y", "y", "n", "n", "n", "n"],
-                ["y", "y", "y", "y", "y", "y", "y", "y", "y", "y"],
-                ["y", "y", "y", "y", "y", "n", "y", "y", "n", "n"],
-                ["n", "n", "n", "n", "n", "n", "n", "n", "n", "n"], // 10, 10
-                ["y", "y", "y", "y", "y", "y", "y", "y", "y", "y"], // A,A
-            ]),
+            hard_array: working.strategy.hard,
+            soft_array: working.strategy.soft,
+            splits_array: working.strategy.split,
+            bet_sizing: blackjack::BetSizing::default(),
+            run_mode: RunMode::LongRun,
+            session_config: blackjack::SessionConfig::default(),
+            precision_target: 0.05,
+            time_budget: 60,
+            compare_rounds: 1_000_000,
+            pinned: Vec::new(),
+            strategy_name: strategies.library.name.clone(),
+            strategies,
+            rules: working.rules,
+            transfer: None,
+            selection: None,
+            cursor: (0, 0),
+            notice: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
         }
     }
 
@@ -106,37 +225,319 @@ impl Component for Chooser {
                 _ => panic!("Unrecognisable entry!"),
             }
         };
-        let active_array = match self.state {
-            State::Hard => &mut self.hard_array,
-            State::Soft => &mut self.soft_array,
-            State::Splits => &mut self.splits_array,
-            State::None => panic!("Shouldn't be here"),
-        };
+        if !matches!(msg, Msg::Notice(_) | Msg::None) {
+            self.notice = None;
+        }
         match msg {
             Msg::ChangeState(state) => {
                 self.state = state;
+                self.transfer = None;
+                self.selection = None;
+                self.cursor = (0, 0);
             }
             Msg::ToggleCell(i, j) => {
-                toggle_item(&mut active_array[[i, j]]);
+                self.remember();
+                toggle_item(&mut self.active_array()[[i, j]]);
+                self.strategies.autosave(self.chart_file());
+            }
+            Msg::SelectRow(i) => {
+                self.selection = Some(Selection {
+                    anchor: (i, 0),
+                    corner: (i, 9),
+                })
+            }
+            Msg::SelectColumn(j) => {
+                self.selection = Some(Selection {
+                    anchor: (0, j),
+                    corner: (self.rows() - 1, j),
+                })
             }
-            Msg::ToggleRow(i) => {
-                for j in 0..10 {
-                    toggle_item(&mut active_array[[i, j]]);
+            Msg::StartSelection(i, j) => {
+                self.cursor = (i, j);
+                self.selection = Some(Selection {
+                    anchor: (i, j),
+                    corner: (i, j),
+                })
+            }
+            Msg::ExtendSelection(i, j) => match &mut self.selection {
+                Some(selection) if selection.corner != (i, j) => {
+                    selection.corner = (i, j);
+                    self.cursor = (i, j);
                 }
+                _ => return false,
+            },
+            Msg::ClearSelection => self.selection = None,
+            Msg::MoveCursor(di, dj, extend) => {
+                let step = |from: usize, by: isize, size: usize| {
+                    (from as isize + by).clamp(0, size as isize - 1) as usize
+                };
+                let from = self.cursor;
+                self.cursor = (step(from.0, di, self.rows()), step(from.1, dj, 10));
+                let anchor = match self.selection {
+                    Some(selection) if extend => selection.anchor,
+                    _ if extend => from,
+                    _ => self.cursor,
+                };
+                self.selection = Some(Selection {
+                    anchor,
+                    corner: self.cursor,
+                });
+            }
+            Msg::ToggleCursor => {
+                let (i, j) = self.cursor;
+                return self.update(Msg::ToggleCell(i, j));
             }
-            Msg::ToggleColumn(j) => {
-                for i in 0..10 {
-                    if i == 8 && self.state == State::Soft {
-                        continue;
+            Msg::SetSelection(action) => {
+                let cells = match self.selection {
+                    Some(selection) => selection.cells(),
+                    None => vec![self.cursor],
+                };
+                self.remember();
+                for (i, j) in cells {
+                    self.active_array()[[i, j]] = action;
+                }
+                self.strategies.autosave(self.chart_file());
+            }
+            Msg::Notice(notice) => self.notice = Some(notice),
+            Msg::ResetToPreset(i) => {
+                let preset = match blackjack::Preset::all().get(i) {
+                    Some(preset) => preset.strategy(),
+                    None => return false,
+                };
+                let source = match self.state {
+                    State::Hard => preset.hard.clone(),
+                    State::Soft => preset.soft.clone(),
+                    State::Splits => preset.split.clone(),
+                    _ => return false,
+                };
+                self.remember();
+                match self.selection {
+                    Some(selection) => {
+                        for (i, j) in selection.cells() {
+                            self.active_array()[[i, j]] = source[[i, j]];
+                        }
+                    }
+                    None => *self.active_array() = source,
+                }
+                self.strategies.autosave(self.chart_file());
+            }
+            Msg::Undo => match self.undo.pop() {
+                Some(chart) => {
+                    self.redo.push(self.chart_file());
+                    self.rules = chart.rules;
+                    self.set_strategy(chart.strategy);
+                    self.strategies.autosave(self.chart_file());
+                }
+                None => return false,
+            },
+            Msg::Redo => match self.redo.pop() {
+                Some(chart) => {
+                    self.undo.push(self.chart_file());
+                    self.rules = chart.rules;
+                    self.set_strategy(chart.strategy);
+                    self.strategies.autosave(self.chart_file());
+                }
+                None => return false,
+            },
+            Msg::TogglePalette => {
+                let colorblind = !self.strategies.library.colorblind;
+                self.strategies.set_colorblind(colorblind);
+            }
+            Msg::CycleBetSizing => {
+                self.bet_sizing = self.bet_sizing.next();
+            }
+            Msg::CycleRunMode => {
+                self.run_mode = match self.run_mode {
+                    RunMode::LongRun => RunMode::Precision,
+                    RunMode::Precision => RunMode::Sessions,
+                    RunMode::Sessions => RunMode::Compare,
+                    RunMode::Compare => RunMode::LongRun,
+                };
+            }
+            Msg::SetSessionField(field, value) => {
+                let value = match value.parse::<usize>() {
+                    Ok(value) => value,
+                    Err(_) => return false,
+                };
+                let config = &mut self.session_config;
+                match field {
+                    SessionField::Sessions => config.sessions = value,
+                    SessionField::Rounds => config.rounds = value,
+                    SessionField::Bankroll => config.bankroll = value,
+                    SessionField::WinGoal => config.win_goal = value,
+                    SessionField::StopLoss => config.stop_loss = value,
+                }
+            }
+            Msg::SetPrecisionTarget(value) => match value.parse::<f64>() {
+                Ok(value) if value > 0. => self.precision_target = value,
+                _ => return false,
+            },
+            Msg::SetTimeBudget(value) => match value.parse::<u32>() {
+                Ok(value) => self.time_budget = value,
+                Err(_) => return false,
+            },
+            Msg::SetCompareRounds(value) => match value.parse::<usize>() {
+                Ok(value) if value > 0 => self.compare_rounds = value,
+                _ => return false,
+            },
+            Msg::TogglePin(name) => match self.pinned.iter().position(|pinned| *pinned == name) {
+                Some(i) => {
+                    self.pinned.remove(i);
+                }
+                None => self.pinned.push(name),
+            },
+            Msg::SetStrategyName(name) => {
+                self.strategy_name = name;
+                return false;
+            }
+            Msg::SaveStrategy => {
+                let name = self.strategy_name.trim().to_string();
+                if name.is_empty() {
+                    return false;
+                }
+                self.strategies.save(&name);
+            }
+            Msg::LoadStrategy(name) => {
+                if let Some(chart) = self.strategies.load(&name) {
+                    self.remember();
+                    self.rules = chart.rules;
+                    self.set_strategy(chart.strategy);
+                    self.strategy_name = name;
+                }
+            }
+            Msg::DuplicateStrategy(name) => self.strategies.duplicate(&name),
+            Msg::DeleteStrategy(name) => {
+                self.pinned.retain(|pinned| *pinned != name);
+                self.strategies.delete(&name);
+            }
+            Msg::LoadPreset(i) => {
+                let preset = match blackjack::Preset::all().get(i) {
+                    Some(&preset) => preset,
+                    None => return false,
+                };
+                self.remember();
+                preset.apply_rules(&mut self.rules);
+                self.set_strategy(preset.strategy());
+                self.strategies.autosave(self.chart_file());
+                self.strategy_name = preset.to_string();
+            }
+            Msg::Export => {
+                self.transfer = Some(Transfer {
+                    link: false,
+                    csv: false,
+                    text: self.chart_file().to_json(),
+                    message: "Copy this chart to share it, or download it as a file.".to_string(),
+                });
+            }
+            Msg::Import => {
+                self.transfer = Some(Transfer {
+                    link: false,
+                    csv: false,
+                    text: String::new(),
+                    message: "Paste a chart in JSON or CSV below, then press Import.".to_string(),
+                });
+            }
+            Msg::Share => {
+                self.transfer = Some(Transfer {
+                    link: true,
+                    csv: false,
+                    text: format!(
+                        "{}{}{}",
+                        page_url(),
+                        LINK_PREFIX,
+                        self.chart_file().to_link_code()
+                    ),
+                    message: "Send this link to share your chart and rules.".to_string(),
+                });
+            }
+            Msg::SetTransferText(text) => {
+                if let Some(transfer) = &mut self.transfer {
+                    transfer.text = text;
+                }
+                return false;
+            }
+            Msg::ToggleTransferFormat => {
+                let chart = self.chart_file();
+                if let Some(transfer) = &mut self.transfer {
+                    transfer.csv = !transfer.csv;
+                    transfer.text = if transfer.csv {
+                        chart.to_csv()
+                    } else {
+                        chart.to_json()
                     };
-                    toggle_item(&mut active_array[[i, j]]);
                 }
             }
+            Msg::ApplyImport => {
+                let text = match &self.transfer {
+                    Some(transfer) => transfer.text.clone(),
+                    None => return false,
+                };
+                match blackjack::ChartFile::parse(&text) {
+                    Ok(chart) => {
+                        self.remember();
+                        self.rules = chart.rules;
+                        self.set_strategy(chart.strategy);
+                        self.strategies.autosave(self.chart_file());
+                        self.transfer = None;
+                    }
+                    Err(error) => {
+                        if let Some(transfer) = &mut self.transfer {
+                            transfer.message = format!("Couldn't import that: {}", error);
+                        }
+                    }
+                }
+            }
+            Msg::CloseTransfer => self.transfer = None,
             Msg::PlayGame => {
-                let outcome =
-                    blackjack::play(&self.hard_array, &self.soft_array, &self.splits_array);
+                let strategy = self.strategy();
+                let rules = self.rules;
+                let request = match self.run_mode {
+                    RunMode::LongRun => simulator::Request::Run(
+                        strategy,
+                        rules,
+                        self.bet_sizing,
+                        blackjack::Stopping::Rounds(blackjack::LONG_RUN_ROUNDS),
+                    ),
+                    RunMode::Precision => simulator::Request::Run(
+                        strategy,
+                        rules,
+                        self.bet_sizing,
+                        blackjack::Stopping::Precision {
+                            target: self.precision_target / 100.,
+                            seconds: self.time_budget,
+                            max_rounds: blackjack::MAX_PRECISION_ROUNDS,
+                        },
+                    ),
+                    RunMode::Sessions => simulator::Request::Sessions(
+                        strategy,
+                        rules,
+                        self.bet_sizing,
+                        self.session_config,
+                    ),
+                    RunMode::Compare => {
+                        let saved = &self.strategies.library.saved;
+                        let mut entrants = vec![(WORKING_NAME.to_string(), strategy)];
+                        entrants.extend(self.pinned.iter().filter_map(|name| {
+                            Some((name.clone(), saved.get(name)?.strategy.clone()))
+                        }));
+                        if entrants.len() < 2 {
+                            return false;
+                        }
+                        simulator::Request::Compare(
+                            entrants,
+                            rules,
+                            self.bet_sizing,
+                            self.compare_rounds,
+                        )
+                    }
+                };
+                self.on_end_game.emit(crate::Msg::StartGame(request));
+                return false;
+            }
+            Msg::Train => {
                 self.on_end_game
-                    .emit(crate::Msg::ChangeState(crate::State::Scores(outcome)));
+                    .emit(crate::Msg::StartTraining(Some(self.chart_file())));
+                return false;
             }
             _ => return false,
         }
@@ -144,11 +545,134 @@ impl Component for Chooser {
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.state != props.state {
+            self.transfer = None;
+            self.selection = None;
+            self.cursor = (0, 0);
+        }
         self.state = props.state;
         true
     }
 
     fn view(&self) -> Html {
+        html! {
+            <div class=("container", "container-chooser", if self.strategies.library.colorblind { "palette-safe" } else { "" }),>
+                <div class=("container", "chooser-info"),>
+                    <Messages: messages=self.messages(),/>
+                </div>
+                {
+                    match (&self.transfer, self.state) {
+                        (Some(transfer), _) => self.view_transfer(transfer),
+                        (None, State::Settings) => self.view_settings(),
+                        (None, _) => self.view_matrix(),
+                    }
+                }
+            </div>
+        }
+    }
+}
+
+impl Chooser {
+    fn strategy(&self) -> blackjack::Strategy {
+        blackjack::Strategy {
+            hard: self.hard_array.clone(),
+            soft: self.soft_array.clone(),
+            split: self.splits_array.clone(),
+        }
+    }
+
+    fn chart_file(&self) -> blackjack::ChartFile {
+        blackjack::ChartFile {
+            rules: self.rules,
+            strategy: self.strategy(),
+        }
+    }
+
+    fn set_strategy(&mut self, strategy: blackjack::Strategy) {
+        self.hard_array = strategy.hard;
+        self.soft_array = strategy.soft;
+        self.splits_array = strategy.split;
+    }
+
+    fn active_array(&mut self) -> &mut Array2<&'static str> {
+        match self.state {
+            State::Hard => &mut self.hard_array,
+            State::Soft => &mut self.soft_array,
+            State::Splits => &mut self.splits_array,
+            _ => panic!("Shouldn't be here"),
+        }
+    }
+
+    /// Keeps the chart as it is now so the next edit can be undone.
+    fn remember(&mut self) {
+        if self.undo.len() == HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(self.chart_file());
+        self.redo.clear();
+    }
+
+    /// Rows in the current table
+    fn rows(&self) -> usize {
+        match self.state {
+            State::Hard => blackjack::HARD_CHART_ROWS,
+            _ => 10,
+        }
+    }
+
+    fn messages(&self) -> Vec<String> {
+        let mut messages = match self.state {
+            State::Hard => vec![
+                "--- HARD HANDS ---".to_string(),
+                "The rows (numbers 4-21) represent the sum total of the cards in your hand, if you do NOT have an Ace.".to_string(),
+                "The columns (numbers 2-A) represent the dealer's card that is face up at the start of the round.".to_string(),
+                "\"S\" means STAND - don't pick up any more cards.".to_string(),
+                "\"H\" means HIT - take another card from the deck.".to_string(),
+                "\"D\" means DOUBLE - double your starting bet and pick up only one more card.".to_string(),
+                "Choose your action by clicking the buttons on the right.".to_string(),
+            ],
+            State::Soft => vec![
+                "--- SOFT HANDS ---".to_string(),
+                "The rows (numbers 2-A) represent the other card if one of your cards is an Ace.".to_string(),
+                "The columns (numbers 2-A) represent the dealer's card that is face up at the start of the round.".to_string(),
+                "\"S\" means STAND - don't pick up any more cards.".to_string(),
+                "\"H\" means HIT - take another card from the deck.".to_string(),
+                "\"D\" means DOUBLE - double your starting bet and pick up only one more card.".to_string(),
+                "Choose your action by clicking the buttons on the right.".to_string(),
+                "The \"10\" row is soft 21. A+10 as your first two cards is a blackjack, but you can reach soft 21 with more cards, like A+5+5.".to_string()
+            ],
+            State::Splits => vec![
+                "--- SPLITS ---".to_string(),
+                "If you have two of the same card, you have the option to SPLIT them in two, creating two separate hands.".to_string(),
+                "The rows (numbers 2-A) represent the card value.".to_string(),
+                "The columns (numbers 2-A) represent the dealer's card that is face up at the start of the round.".to_string(),
+                "\"Y\" means YES - split your hand in two.".to_string(),
+                "\"N\" means NO - don't split your hand in two.".to_string(),
+                "Choose your action by clicking the buttons on the right.".to_string(),
+            ],
+            State::Settings => vec![
+                "--- SETTINGS ---".to_string(),
+                "\"Bets\" changes how much you bet each round: a flat unit, a Hi-Lo bet ramp, or a fraction of the Kelly bet.".to_string(),
+                "\"Mode\" picks between one long run, a run that stops once it's precise enough, many short sessions, and a head to head between charts.".to_string(),
+                "A long run plays 2 million hands from a $1,000,000 bankroll, and its score goes on the High Scores board.".to_string(),
+                "\"Until precise\" keeps playing until the standard error of the EV drops to the target, or the time budget runs out.".to_string(),
+                "Sessions start from a trip bankroll and stop at the round limit, the win goal, the stop-loss, or when you go broke.".to_string(),
+                "Set the win goal or stop-loss to 0 to turn it off.".to_string(),
+                "\"Head to head\" plays your chart and the saved charts you Pin on exactly the same cards, to tell close strategies apart.".to_string(),
+                "\"Colors\" switches the chart to colors that can be told apart without seeing red and green.".to_string(),
+                "Pick a Preset to load a ready-made chart, like basic strategy for your table, to start from or compare against.".to_string(),
+                "Your charts are kept in this browser as you edit them. Give them a name and Save to keep a copy you can Load later.".to_string(),
+                "Press Run when you're ready!".to_string(),
+            ],
+            _ => vec!["An error has occured :(".to_string()],
+        };
+        if matches!(self.state, State::Hard | State::Soft | State::Splits) {
+            messages.extend(editing_help(self.state));
+        }
+        messages
+    }
+
+    fn view_matrix(&self) -> Html {
         let convert_state = |s: &str| match s {
             "s" => action_button::State::S,
             "h" => action_button::State::H,
@@ -157,78 +681,358 @@ impl Component for Chooser {
             "n" => action_button::State::N,
             _ => panic!("Unrecognisable state"),
         };
+        let selected = |i: usize, j: usize| matches!(self.selection, Some(s) if s.contains(i, j));
+        let state = self.state;
         html! {
-            <div class=("container", "container-chooser"),>
-                <div class=("container", "chooser-info"),>
-                    <Messages: messages= match self.state {
-                        State::Hard =>  vec![
-                            "--- HARD HANDS ---".to_string(),
-                            "The rows (numbers 9-18) represent the sum total of the cards in your hand, if you do NOT have an Ace.".to_string(),
-                            "The columns (numbers 2-A) represent the dealer's card that is face up at the start of the round.".to_string(),
-                            "\"S\" means STAND - don't pick up any more cards.".to_string(),
-                            "\"H\" means HIT - take another card from the deck.".to_string(),
-                            "\"D\" means DOUBLE - double your starting bet and pick up only one more card.".to_string(),
-                            "Choose your action by clicking the buttons on the right.".to_string(),
-                            "The yellow buttons toggle the entire row / column.".to_string(),
-                        ],
-                        State::Soft => vec![
-                            "--- SOFT HANDS ---".to_string(),
-                            "The rows (numbers 2-A) represent the other card if one of your cards is an Ace.".to_string(),
-                            "The columns (numbers 2-A) represent the dealer's card that is face up at the start of the round.".to_string(),
-                            "\"S\" means STAND - don't pick up any more cards.".to_string(),
-                            "\"H\" means HIT - take another card from the deck.".to_string(),
-                            "\"D\" means DOUBLE - double your starting bet and pick up only one more card.".to_string(),
-                            "Choose your action by clicking the buttons on the right.".to_string(),
-                            "The yellow buttons toggle the entire row / column.".to_string(),
-                            "Note that you cannot toggle the \"10\" row, becuase A+10 is already 21.".to_string()
-                        ],
-                        State::Splits => vec![
-                            "--- SPLITS ---".to_string(),
-                            "If you have two of the same card, you have the option to SPLIT them in two, creating two separate hands.".to_string(),
-                            "The rows (numbers 2-A) represent the card value.".to_string(),
-                            "The columns (numbers 2-A) represent the dealer's card that is face up at the start of the round.".to_string(),
-                            "\"Y\" means YES - split your hand in two.".to_string(),
-                            "\"N\" means NO - don't split your hand in two.".to_string(),
-                            "Choose your action by clicking the buttons on the right.".to_string(),
-                            "The yellow buttons toggle the entire row / column.".to_string(),
-                        ],
-                        _ => vec!["An error has occured :(".to_string()]
-                    },/>
+            <div class=("chooser-editor", "container"),>
+            <div class=("chooser-matrix", "container", if self.rows() > 10 { "matrix-tall" } else { "" }), role="grid", tabindex="0",
+                aria-label=format!("{} chart. Arrow keys move, Shift and an arrow selects, letters set the action.", self.table_name()),
+                aria-activedescendant=cell_id(self.cursor.0, self.cursor.1),
+                onkeydown=self.link.callback(move |e: KeyDownEvent| match grid_key(&e, state) {
+                    Some(msg) => {
+                        e.prevent_default();
+                        msg
+                    }
+                    None => Msg::None,
+                }),>
+                <div class="chooser-row", role="row",>
+                <span role="columnheader", aria-label="Your hand",/>
+                { for (0..10).map(|j| html! {
+                    <ActionButton: state=action_button::State::T(match j {
+                        9 => 99,
+                        _ => j + 2
+                    }), onsignal=self.link.callback(move |_| Msg::SelectColumn(j)), active=true,
+                    role="columnheader", label=format!("Select dealer {} column", card_name(j)),/>
+                })}
                 </div>
-                <div class=("chooser-matrix", "container"),>
-                    <span />
-                    { for (0..10).map(|j| html! {
-                        <ActionButton: state=action_button::State::T(match j {
-                            9 => 99,
-                            _ => j + 2
-                        }), onsignal=self.link.callback(move |_| Msg::ToggleColumn(j)), active=true,/>
+                { for (0..self.rows()).map(|i|
+                    {
+                        html! {
+                            <div class="chooser-row", role="row",>
+                            <ActionButton: state=action_button::State::T(match self.state {
+                                State::Hard => i + blackjack::LOWEST_HARD,
+                                _ => match i {
+                                    9 => 99,
+                                    _ => i + 2},
+                            }), onsignal=self.link.callback(move |_| Msg::SelectRow(i)), active=true,
+                            role="rowheader", label=format!("Select {} row", self.hand_name(i)),/>
+                            { for (0..10).map(|j| {
+                                let action = match self.state {
+                                    State::Hard => self.hard_array[[i,j]],
+                                    State::Soft => self.soft_array[[i,j]],
+                                    State::Splits => self.splits_array[[i,j]],
+                                    _ => panic!("Shouldn't be here"),
+                                };
+                                html! {
+                                <ActionButton: state=convert_state(action), onsignal=self.link.callback(move |_| Msg::ToggleCell(i, j)), active=true,
+                                onselect=Some(self.link.callback(move |start| if start {
+                                    Msg::StartSelection(i, j)
+                                } else {
+                                    Msg::ExtendSelection(i, j)
+                                })), selected=selected(i, j), cursor=(self.cursor == (i, j)),
+                                role="gridcell", id=cell_id(i, j),
+                                label=format!("{} versus dealer {}: {}", self.hand_name(i), card_name(j), action_name(action)),/>
+                                }
+                            })}
+                            </div>
+                        }
+                    }
+                )}
+            </div>
+            { self.view_tools() }
+            </div>
+        }
+    }
+
+    fn table_name(&self) -> &'static str {
+        match self.state {
+            State::Hard => "Hard hands",
+            State::Soft => "Soft hands",
+            _ => "Splits",
+        }
+    }
+
+    /// How row `i` of the current table reads out, like "hard 12" or "soft A,7"
+    fn hand_name(&self, i: usize) -> String {
+        match self.state {
+            State::Hard => format!("hard {}", i + blackjack::LOWEST_HARD),
+            State::Soft if i == 8 => "soft 21".to_string(),
+            State::Soft => format!("soft A,{}", card_name(i)),
+            _ => format!("pair of {}s", card_name(i)),
+        }
+    }
+
+    /// The editing buttons under the grid
+    fn view_tools(&self) -> Html {
+        let actions: &[(&'static str, &str, &str, &str)] = match self.state {
+            State::Splits => &[
+                ("y", "Y", "green", "action-y"),
+                ("n", "N", "red", "action-n"),
+            ],
+            _ => &[
+                ("s", "S", "red", "action-s"),
+                ("h", "H", "blue", "action-h"),
+                ("d", "D", "green", "action-d"),
+            ],
+        };
+        let active = |on: bool| if on { "active" } else { "inactive" };
+        let selecting = self.selection.is_some();
+        html! {
+            <div class="chooser-tools",>
+                <button class=("btn-3d", "cyan", "btn-small", active(!self.undo.is_empty())), onclick=self.link.callback(|_| Msg::Undo),>{ "Undo" }</button>
+                <button class=("btn-3d", "cyan", "btn-small", active(!self.redo.is_empty())), onclick=self.link.callback(|_| Msg::Redo),>{ "Redo" }</button>
+                <span>{ "Set selection to" }</span>
+                { for actions.iter().map(|&(action, label, color, palette)| html! {
+                    <button class=("btn-3d", color, palette, "btn-small", active(selecting)), aria-label=format!("Set selection to {}", action_name(action)), onclick=self.link.callback(move |_| Msg::SetSelection(action)),>{ label }</button>
+                })}
+                <button class=("btn-3d", "yellow", "btn-small", active(selecting)), onclick=self.link.callback(|_| Msg::ClearSelection),>{ "Clear" }</button>
+                <select class="tools-select", onchange=self.link.callback(|e: ChangeData| match e {
+                    ChangeData::Select(select) => select.value().and_then(|i| i.parse().ok()).map_or(Msg::None, Msg::ResetToPreset),
+                    _ => Msg::None,
+                }),>
+                    <option value="",>{ if selecting { "Reset selection to..." } else { "Reset chart to..." } }</option>
+                    { for blackjack::Preset::all().iter().enumerate().map(|(i, preset)| html! {
+                        <option value=i.to_string(),>{ preset.to_string() }</option>
                     })}
-                    { for (0..10).map(|i|
-                        {
-                            html! {
+                </select>
+                <div class="tools-notice", role="status", aria-live="polite",>{ self.notice.unwrap_or("") }</div>
+            </div>
+        }
+    }
+
+    fn view_settings(&self) -> Html {
+        let config = self.session_config;
+        let fields = [
+            (SessionField::Sessions, "Sessions", config.sessions),
+            (SessionField::Rounds, "Rounds per session", config.rounds),
+            (SessionField::Bankroll, "Bankroll ($)", config.bankroll),
+            (SessionField::WinGoal, "Win goal ($)", config.win_goal),
+            (SessionField::StopLoss, "Stop-loss ($)", config.stop_loss),
+        ];
+        html! {
+            <div class=("chooser-settings", "container"),>
+                <span>{ "Bets" }</span>
+                <button class=("btn-3d", "cyan", "active"), onclick=self.link.callback(|_| Msg::CycleBetSizing),>{ self.bet_sizing.to_string() }</button>
+                <span>{ "Mode" }</span>
+                <button class=("btn-3d", "cyan", "active"), onclick=self.link.callback(|_| Msg::CycleRunMode),>{
+                    match self.run_mode {
+                        RunMode::LongRun => "Long run",
+                        RunMode::Precision => "Until precise",
+                        RunMode::Sessions => "Sessions",
+                        RunMode::Compare => "Head to head",
+                    }
+                }</button>
+                {
+                    match self.run_mode {
+                        RunMode::Sessions => html! {
+                            <>
+                            { for fields.iter().map(|&(field, label, value)| html! {
                                 <>
-                                <ActionButton: state=action_button::State::T(match self.state {
-                                    State::Hard => i + 9,
-                                    _ => match i {
-                                        9 => 99,
-                                        _ => i + 2},
-                                }), onsignal=self.link.callback(move |_| Msg::ToggleRow(i)), active=(i!=8 || self.state != State::Soft),/>
-                                { for (0..10).map(|j| html! {
-                                    <ActionButton: state=convert_state(
-                                        match self.state {
-                                            State::Hard => self.hard_array[[i,j]],
-                                            State::Soft => self.soft_array[[i,j]],
-                                            State::Splits => self.splits_array[[i,j]],
-                                            State::None => panic!("Shouldn't be here"),
-                                        }
-                                    ), onsignal=self.link.callback(move |_| Msg::ToggleCell(i, j)), active=(i!=8 || self.state != State::Soft),/>
-                                })}
+                                <span>{ label }</span>
+                                <input class="settings-input", type="number", min="0", value=value.to_string(), oninput=self.link.callback(move |e: InputData| Msg::SetSessionField(field, e.value)),/>
                                 </>
-                            }
+                            })}
+                            </>
+                        },
+                        RunMode::Precision => html! {
+                            <>
+                            <span>{ "Target std. error (%)" }</span>
+                            <input class="settings-input", type="number", min="0", step="0.01", value=self.precision_target.to_string(), oninput=self.link.callback(|e: InputData| Msg::SetPrecisionTarget(e.value)),/>
+                            <span>{ "Time budget (s)" }</span>
+                            <input class="settings-input", type="number", min="0", value=self.time_budget.to_string(), oninput=self.link.callback(|e: InputData| Msg::SetTimeBudget(e.value)),/>
+                            </>
+                        },
+                        RunMode::Compare => html! {
+                            <>
+                            <span>{ "Hands each" }</span>
+                            <input class="settings-input", type="number", min="1", value=self.compare_rounds.to_string(), oninput=self.link.callback(|e: InputData| Msg::SetCompareRounds(e.value)),/>
+                            <span>{ "Comparing" }</span>
+                            <span>{
+                                if self.pinned.is_empty() {
+                                    "Pin some saved charts below".to_string()
+                                } else {
+                                    format!("{} vs {}", WORKING_NAME, self.pinned.join(", "))
+                                }
+                            }</span>
+                            </>
+                        },
+                        RunMode::LongRun => html! {<span />},
+                    }
+                }
+                <span>{ "Rules" }</span>
+                <span>{
+                    format!(
+                        "{} decks, dealer {} soft 17",
+                        self.rules.decks,
+                        if self.rules.dealer_hits_soft_17 { "hits" } else { "stands on" }
+                    )
+                }</span>
+                <span>{ "Colors" }</span>
+                <button class=("btn-3d", "cyan", "active"), onclick=self.link.callback(|_| Msg::TogglePalette),>{
+                    if self.strategies.library.colorblind { "Colorblind-safe" } else { "Standard" }
+                }</button>
+                <span>{ "Preset" }</span>
+                <select class="settings-input", onchange=self.link.callback(|e: ChangeData| match e {
+                    ChangeData::Select(select) => select.value().and_then(|i| i.parse().ok()).map_or(Msg::None, Msg::LoadPreset),
+                    _ => Msg::None,
+                }),>
+                    <option value="",>{ "Load a preset..." }</option>
+                    { for blackjack::Preset::all().iter().enumerate().map(|(i, preset)| html! {
+                        <option value=i.to_string(),>{ preset.to_string() }</option>
+                    })}
+                </select>
+                <span>{ "Strategy name" }</span>
+                <input class="settings-input", type="text", value=self.strategy_name.clone(), oninput=self.link.callback(|e: InputData| Msg::SetStrategyName(e.value)),/>
+                <span />
+                <button class=("btn-3d", "green", "active"), onclick=self.link.callback(|_| Msg::SaveStrategy),>{ " Save " }</button>
+                { for self.strategies.library.saved.keys().map(|name| self.view_saved(name)) }
+            </div>
+        }
+    }
+
+    fn view_saved(&self, name: &str) -> Html {
+        let (load, duplicate, delete, pin) = (
+            name.to_string(),
+            name.to_string(),
+            name.to_string(),
+            name.to_string(),
+        );
+        let pinned = self.pinned.iter().any(|pinned| pinned == name);
+        html! {
+            <>
+            <span class=(if name == self.strategies.library.name { "saved-current" } else { "" }),>{ name }</span>
+            <span>
+                <button class=("btn-3d", "blue", "active", "btn-small"), onclick=self.link.callback(move |_| Msg::LoadStrategy(load.clone())),>{ "Load" }</button>
+                <button class=("btn-3d", "cyan", "active", "btn-small"), onclick=self.link.callback(move |_| Msg::DuplicateStrategy(duplicate.clone())),>{ "Copy" }</button>
+                <button class=("btn-3d", "red", "active", "btn-small"), onclick=self.link.callback(move |_| Msg::DeleteStrategy(delete.clone())),>{ "Delete" }</button>
+                <button class=("btn-3d", "yellow", "active", "btn-small"), onclick=self.link.callback(move |_| Msg::TogglePin(pin.clone())),>{ if pinned { "Unpin" } else { "Pin" } }</button>
+            </span>
+            </>
+        }
+    }
+
+    fn view_transfer(&self, transfer: &Transfer) -> Html {
+        let (extension, format) = if transfer.csv {
+            ("csv", "CSV")
+        } else {
+            ("json", "JSON")
+        };
+        html! {
+            <div class=("chooser-transfer", "container"),>
+                <textarea class="transfer-text", value=transfer.text.clone(), oninput=self.link.callback(|e: InputData| Msg::SetTransferText(e.value)),/>
+                <div>{ &transfer.message }</div>
+                <div>
+                {
+                    if transfer.link {
+                        html! {<span />}
+                    } else {
+                        html! {
+                            <>
+                            <button class=("btn-3d", "cyan", "active"), onclick=self.link.callback(|_| Msg::ToggleTransferFormat),>{ format }</button>
+                            <a class=("btn-3d", "blue", "active"), href=format!("data:text/plain;charset=utf-8,{}", percent_encode(&transfer.text)), download=format!("strategy.{}", extension),>{ "Download" }</a>
+                            <button class=("btn-3d", "green", "active"), onclick=self.link.callback(|_| Msg::ApplyImport),>{ "Import" }</button>
+                            </>
                         }
-                    )}
+                    }
+                }
+                    <button class=("btn-3d", "red", "active"), onclick=self.link.callback(|_| Msg::CloseTransfer),>{ "Close" }</button>
                 </div>
             </div>
         }
     }
 }
+
+/// How to edit a table, which follows each table's own help
+fn editing_help(state: State) -> Vec<String> {
+    let keys = match state {
+        State::Splits => "P or Y splits, N doesn't",
+        _ => "S, H and D set the action",
+    };
+    vec![
+        "Drag across the grid, or click a yellow button, to select a block of cells. The buttons underneath then set them all at once, or reset them to a preset.".to_string(),
+        "Undo and Redo step back and forth through your edits.".to_string(),
+        format!("With the keyboard, the arrow keys move around the grid and Shift with an arrow selects. {}, Space cycles it, and Ctrl+Z undoes.", keys),
+    ]
+}
+
+/// What a key pressed on the grid does, if anything.
+fn grid_key(e: &KeyDownEvent, state: State) -> Option<Msg> {
+    let shift = e.shift_key();
+    if e.ctrl_key() || e.meta_key() {
+        return match e.key().as_str() {
+            "z" | "Z" if shift => Some(Msg::Redo),
+            "z" | "Z" => Some(Msg::Undo),
+            "y" | "Y" => Some(Msg::Redo),
+            _ => None,
+        };
+    }
+    let action = match (state, e.key().to_lowercase().as_str()) {
+        (_, "arrowup") => return Some(Msg::MoveCursor(-1, 0, shift)),
+        (_, "arrowdown") => return Some(Msg::MoveCursor(1, 0, shift)),
+        (_, "arrowleft") => return Some(Msg::MoveCursor(0, -1, shift)),
+        (_, "arrowright") => return Some(Msg::MoveCursor(0, 1, shift)),
+        (_, " ") | (_, "enter") => return Some(Msg::ToggleCursor),
+        (_, "escape") => return Some(Msg::ClearSelection),
+        (_, "r") => return Some(Msg::Notice(SURRENDER_NOTICE)),
+        (State::Splits, "p") | (State::Splits, "y") => "y",
+        (State::Splits, "n") => "n",
+        (State::Splits, _) => return None,
+        (_, "s") => "s",
+        (_, "h") => "h",
+        (_, "d") => "d",
+        _ => return None,
+    };
+    Some(Msg::SetSelection(action))
+}
+
+fn cell_id(i: usize, j: usize) -> String {
+    format!("chooser-cell-{}-{}", i, j)
+}
+
+/// The card for row or column `i` of a chart
+fn card_name(i: usize) -> String {
+    match i {
+        9 => "A".to_string(),
+        _ => (i + 2).to_string(),
+    }
+}
+
+fn action_name(code: &str) -> &'static str {
+    match code {
+        "s" => "Stand",
+        "h" => "Hit",
+        "d" => "Double",
+        "y" => "Split",
+        _ => "Don't split",
+    }
+}
+
+/// The chart in the page's link, if it was opened from a shared one.
+pub fn linked_chart() -> Option<blackjack::ChartFile> {
+    let hash = window().location()?.hash().ok()?;
+    if !hash.starts_with(LINK_PREFIX) {
+        return None;
+    }
+    blackjack::ChartFile::from_link_code(&hash[LINK_PREFIX.len()..]).ok()
+}
+
+/// The page's address without its fragment
+fn page_url() -> String {
+    let href = window()
+        .location()
+        .and_then(|location| location.href().ok())
+        .unwrap_or_default();
+    href.split('#').next().unwrap_or_default().to_string()
+}
+
+/// Escapes `text` for a data URL.
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -9,11 +9,16 @@ pub struct Controls {
     onsignal: Callback<crate::Msg>,
     link: ComponentLink<Self>,
     chooser_link: Option<ComponentLink<Chooser>>,
+    progress: Option<f64>,
 }
 
 pub enum Msg {
     ButtonPressed(crate::Msg),
     PlayGame,
+    TrainChart,
+    Export,
+    Import,
+    Share,
 }
 
 #[derive(Properties, Clone, Debug)]
@@ -24,6 +29,8 @@ pub struct Props {
     pub state: State,
     #[props(required)]
     pub chooser_link: Option<ComponentLink<Chooser>>,
+    /// How far through the current simulation we are, if one is running
+    pub progress: Option<f64>,
 }
 
 impl Component for Controls {
@@ -36,6 +43,7 @@ impl Component for Controls {
             onsignal: props.onsignal,
             link,
             chooser_link: props.chooser_link,
+            progress: props.progress,
         }
     }
 
@@ -46,6 +54,22 @@ impl Component for Controls {
                 Some(comp) => comp.callback(|_| chooser::Msg::PlayGame).emit(()),
                 None => panic!("Link not found"),
             },
+            Msg::TrainChart => match &self.chooser_link {
+                Some(comp) => comp.callback(|_| chooser::Msg::Train).emit(()),
+                None => panic!("Link not found"),
+            },
+            Msg::Export => match &self.chooser_link {
+                Some(comp) => comp.callback(|_| chooser::Msg::Export).emit(()),
+                None => panic!("Link not found"),
+            },
+            Msg::Import => match &self.chooser_link {
+                Some(comp) => comp.callback(|_| chooser::Msg::Import).emit(()),
+                None => panic!("Link not found"),
+            },
+            Msg::Share => match &self.chooser_link {
+                Some(comp) => comp.callback(|_| chooser::Msg::Share).emit(()),
+                None => panic!("Link not found"),
+            },
         }
         false
     }
@@ -54,6 +78,7 @@ impl Component for Controls {
         self.state = props.state;
         self.onsignal = props.onsignal;
         self.chooser_link = props.chooser_link;
+        self.progress = props.progress;
         true
     }
 
@@ -63,34 +88,43 @@ impl Component for Controls {
             State::Welcome => html! {<>
                 <span>
                     <button class=("btn-3d", "green", "inactive"),>{ " Code " }</button>
+                    <button class=("btn-3d", "yellow", "active"), onclick=self.link.callback(move |_| Msg::ButtonPressed(ChangeState(State::Playing))),>{ " Table "}</button>
                 </span>
                 <span>
-                    <button class=("btn-3d", "blue", "inactive"),>{ "High Scores" }</button>
+                    <button class=("btn-3d", "blue", "active"), onclick=self.link.callback(move |_| Msg::ButtonPressed(ChangeState(State::HighScores))),>{ "High Scores" }</button>
+                    <button class=("btn-3d", "cyan", "active"), onclick=self.link.callback(move |_| Msg::ButtonPressed(StartTraining(None))),>{ " Drill "}</button>
                 </span>
                 <span>
                     <button class=("btn-3d", "purple", "active"), onclick=self.link.callback(move |_| Msg::ButtonPressed(ChangeState(State::Choosing(chooser::State::Hard)))),>{ " Play "}</button>
+                    <button class=("btn-3d", "red", "active"), onclick=self.link.callback(move |_| Msg::ButtonPressed(ChangeState(State::Counting))),>{ " Count "}</button>
                 </span>
             </>},
             State::Choosing(choose_state) => {
                 let prev_state = match choose_state {
                     chooser::State::Soft => chooser::State::Hard,
                     chooser::State::Splits => chooser::State::Soft,
+                    chooser::State::Settings => chooser::State::Splits,
                     _ => chooser::State::None,
                 };
                 let next_state = match choose_state {
                     chooser::State::Hard => chooser::State::Soft,
                     chooser::State::Soft => chooser::State::Splits,
+                    chooser::State::Splits => chooser::State::Settings,
                     _ => chooser::State::None,
                 };
                 html! {
                     <>
                     <span>
                         <button class=("btn-3d", "green", "active"), onclick=self.link.callback(move |_| Msg::ButtonPressed(ChangeState(State::Welcome))),>{ " Main Menu "}</button>
+                        <button class=("btn-3d", "cyan", "active"), onclick=self.link.callback(move |_| Msg::TrainChart),>{ " Drill "}</button>
                     </span>
                     <span>
+                    <button class=("btn-3d", "yellow", "active"), onclick=self.link.callback(move |_| Msg::Export),>{ " Export "}</button>
+                    <button class=("btn-3d", "yellow", "active"), onclick=self.link.callback(move |_| Msg::Import),>{ " Import "}</button>
+                    <button class=("btn-3d", "yellow", "active"), onclick=self.link.callback(move |_| Msg::Share),>{ " Share "}</button>
                     {
                         match prev_state {
-                            chooser::State::Hard | chooser::State::Soft => html! {
+                            chooser::State::Hard | chooser::State::Soft | chooser::State::Splits => html! {
                                 <button class=("btn-3d", "blue", "active"), onclick=self.link.callback(move |_| Msg::ButtonPressed(ChangeState(State::Choosing(prev_state)))),>{ " < "}</button>
                             },
                             _ => html! {<span />}
@@ -100,7 +134,7 @@ impl Component for Controls {
                     <span>
                     {
                         match next_state {
-                            chooser::State::Soft | chooser::State::Splits => html! {
+                            chooser::State::Soft | chooser::State::Splits | chooser::State::Settings => html! {
                                 <button class=("btn-3d", "blue", "active"), onclick=self.link.callback(move |_| Msg::ButtonPressed(ChangeState(State::Choosing(next_state)))),>{ " > "}</button>
                             },
                             _ => html! {
@@ -120,6 +154,14 @@ impl Component for Controls {
                     <button class=("btn-3d", "purple", "active"), onclick=self.link.callback(move |_| Msg::ButtonPressed(ChangeState(State::Welcome))),>{ " Main Menu "}</button>
                 </span>
                 <span>
+                {
+                    match self.progress {
+                        Some(progress) => html! {
+                            <button class=("btn-3d", "red", "active"), onclick=self.link.callback(move |_| Msg::ButtonPressed(StopGame)),>{ format!(" Stop ({:.0}%) ", progress * 100.) }</button>
+                        },
+                        None => html! {<span />},
+                    }
+                }
                 </span>
                 </>
             },
@@ -0,0 +1,284 @@
+use crate::blackjack;
+use crate::components::messages::Messages;
+use std::time::Duration;
+use stdweb::web::Date;
+use yew::prelude::*;
+use yew::services::{interval::IntervalTask, IntervalService};
+
+const HISTORY_SHOWN: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Ready,
+    Flashing,
+    Answering,
+}
+
+/// One round of the drill, kept to show progress over time
+struct Attempt {
+    cards: usize,
+    speed: u32,
+    running_right: bool,
+    true_right: bool,
+    /// How long the answer took, in seconds
+    seconds: f64,
+}
+
+/// Flashes cards from a shoe, then asks for the running and true count.
+pub struct CountDrill {
+    link: ComponentLink<Self>,
+    interval: IntervalService,
+    task: Option<IntervalTask>,
+    drill: blackjack::CountDrill,
+    phase: Phase,
+    pairs: bool,
+    /// Milliseconds each flash stays up
+    speed: u32,
+    /// Flashes per round
+    flashes: usize,
+    flashed: usize,
+    shown: Vec<String>,
+    running_answer: String,
+    true_answer: String,
+    /// When the question was asked, in milliseconds since the epoch
+    asked: f64,
+    history: Vec<Attempt>,
+    message: String,
+}
+
+pub enum Msg {
+    TogglePairs,
+    SetSpeed(String),
+    SetFlashes(String),
+    Start,
+    Flash,
+    SetRunning(String),
+    SetTrue(String),
+    Check,
+}
+
+impl Component for CountDrill {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        CountDrill {
+            link,
+            interval: IntervalService::new(),
+            task: None,
+            drill: blackjack::CountDrill::new(&blackjack::Rules::default()),
+            phase: Phase::Ready,
+            pairs: false,
+            speed: 1000,
+            flashes: 10,
+            flashed: 0,
+            shown: Vec::new(),
+            running_answer: String::new(),
+            true_answer: String::new(),
+            asked: 0.,
+            history: Vec::new(),
+            message: "Press Start and keep the Hi-Lo count as the cards go by.".to_string(),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::TogglePairs => self.pairs = !self.pairs,
+            Msg::SetSpeed(value) => match value.parse::<u32>() {
+                Ok(value) if value > 0 => self.speed = value,
+                _ => return false,
+            },
+            Msg::SetFlashes(value) => match value.parse::<usize>() {
+                Ok(value) if value > 0 => self.flashes = value,
+                _ => return false,
+            },
+            Msg::Start => {
+                let shuffled = self.drill.start(self.flashes * self.cards_per_flash());
+                self.message = if shuffled {
+                    "Fresh shoe, so the count starts from 0.".to_string()
+                } else {
+                    format!(
+                        "The count carries on from the last {} cards.",
+                        self.drill.seen()
+                    )
+                };
+                self.phase = Phase::Flashing;
+                self.flashed = 0;
+                self.shown = Vec::new();
+                let callback = self.link.callback(|_| Msg::Flash);
+                self.task = Some(
+                    self.interval
+                        .spawn(Duration::from_millis(self.speed as u64), callback),
+                );
+            }
+            Msg::Flash => {
+                if self.flashed == self.flashes {
+                    self.task = None;
+                    self.shown = Vec::new();
+                    self.phase = Phase::Answering;
+                    self.running_answer = String::new();
+                    self.true_answer = String::new();
+                    self.asked = Date::now();
+                    self.message = "What's the running count and the true count?".to_string();
+                } else {
+                    self.shown = self.drill.flash(self.cards_per_flash());
+                    self.flashed += 1;
+                }
+            }
+            Msg::SetRunning(value) => {
+                self.running_answer = value;
+                return false;
+            }
+            Msg::SetTrue(value) => {
+                self.true_answer = value;
+                return false;
+            }
+            Msg::Check => {
+                let (running, true_count) = match (
+                    self.running_answer.trim().parse::<isize>(),
+                    self.true_answer.trim().parse::<f64>(),
+                ) {
+                    (Ok(running), Ok(true_count)) => (running, true_count),
+                    _ => {
+                        self.message = "Enter both counts as numbers.".to_string();
+                        return true;
+                    }
+                };
+                let (running_right, true_right) = self.drill.check(running, true_count);
+                self.history.push(Attempt {
+                    cards: self.flashes * self.cards_per_flash(),
+                    speed: self.speed,
+                    running_right,
+                    true_right,
+                    seconds: (Date::now() - self.asked) / 1000.,
+                });
+                self.message = format!(
+                    "Running count {} ({}), true count {:+.1} ({})",
+                    self.drill.running_count(),
+                    if running_right { "right" } else { "wrong" },
+                    self.drill.true_count(),
+                    if true_right { "right" } else { "wrong" },
+                );
+                self.phase = Phase::Ready;
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class=("container-feature", "container", "container-trainer"),>
+                <div class=("container", "trainer-question"),>
+                    <div class="play-hand",>
+                        { for self.shown.iter().map(|card| html! { <div class="playing-card",>{ card }</div> }) }
+                    </div>
+                    { self.view_phase() }
+                    <div class="trainer-feedback",>{ &self.message }</div>
+                </div>
+                <Messages: messages=self.stats(),/>
+            </div>
+        }
+    }
+}
+
+impl CountDrill {
+    fn cards_per_flash(&self) -> usize {
+        if self.pairs {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn view_phase(&self) -> Html {
+        match self.phase {
+            Phase::Ready => html! {
+                <div class=("chooser-settings", "container"),>
+                    <span>{ "Cards per flash" }</span>
+                    <button class=("btn-3d", "cyan", "active"), onclick=self.link.callback(|_| Msg::TogglePairs),>{ if self.pairs { "Pairs" } else { "One" } }</button>
+                    <span>{ "Flash time (ms)" }</span>
+                    <input class="settings-input", type="number", min="50", step="50", value=self.speed.to_string(), oninput=self.link.callback(|e: InputData| Msg::SetSpeed(e.value)),/>
+                    <span>{ "Flashes" }</span>
+                    <input class="settings-input", type="number", min="1", value=self.flashes.to_string(), oninput=self.link.callback(|e: InputData| Msg::SetFlashes(e.value)),/>
+                    <span />
+                    <button class=("btn-3d", "purple", "active"), onclick=self.link.callback(|_| Msg::Start),>{ " Start " }</button>
+                </div>
+            },
+            Phase::Flashing => html! {
+                <div>{ format!("{} / {}", self.flashed, self.flashes) }</div>
+            },
+            Phase::Answering => html! {
+                <div class=("chooser-settings", "container"),>
+                    <span>{ "Running count" }</span>
+                    <input class="settings-input", type="number", value=self.running_answer.clone(), oninput=self.link.callback(|e: InputData| Msg::SetRunning(e.value)),/>
+                    <span>{ "True count" }</span>
+                    <input class="settings-input", type="number", step="0.5", value=self.true_answer.clone(), oninput=self.link.callback(|e: InputData| Msg::SetTrue(e.value)),/>
+                    <span />
+                    <button class=("btn-3d", "purple", "active"), onclick=self.link.callback(|_| Msg::Check),>{ " Check " }</button>
+                </div>
+            },
+        }
+    }
+
+    /// Accuracy and answer time overall, then the most recent rounds.
+    fn stats(&self) -> Vec<String> {
+        let mut stats = vec![
+            "--- COUNT DRILL ---".to_string(),
+            "2-6 count +1, 7-9 count 0, and 10s and aces count -1.".to_string(),
+            "The true count is the running count divided by the decks left in the shoe."
+                .to_string(),
+            format!(
+                "True counts within {} of the exact value are marked right.",
+                blackjack::TRUE_COUNT_TOLERANCE
+            ),
+        ];
+        let rounds = self.history.len();
+        if rounds == 0 {
+            return stats;
+        }
+        let right = |f: fn(&Attempt) -> bool| self.history.iter().filter(|a| f(a)).count();
+        stats.push(format!(
+            "Running count: {}/{} right, true count: {}/{} right",
+            right(|a| a.running_right),
+            rounds,
+            right(|a| a.true_right),
+            rounds
+        ));
+        stats.push(format!(
+            "Average answer time: {:.1}s",
+            self.history.iter().map(|a| a.seconds).sum::<f64>() / rounds as f64
+        ));
+        if let Some(fastest) = self
+            .history
+            .iter()
+            .filter(|a| a.running_right && a.true_right)
+            .map(|a| a.speed)
+            .min()
+        {
+            stats.push(format!("Fastest flash time with both right: {}ms", fastest));
+        }
+        stats.extend(
+            self.history
+                .iter()
+                .enumerate()
+                .rev()
+                .take(HISTORY_SHOWN)
+                .map(|(i, a)| {
+                    format!(
+                        "#{}: {} cards at {}ms - running {}, true {} - {:.1}s",
+                        i + 1,
+                        a.cards,
+                        a.speed,
+                        if a.running_right { "✓" } else { "✗" },
+                        if a.true_right { "✓" } else { "✗" },
+                        a.seconds
+                    )
+                }),
+        );
+        stats
+    }
+}
@@ -0,0 +1,196 @@
+use crate::blackjack::{self, ScoreEntry};
+use crate::components::messages::Messages;
+use crate::leaderboard;
+use crate::storage::Scores;
+use yew::prelude::*;
+use yew::services::fetch::{FetchService, FetchTask};
+
+const ENTRIES_SHOWN: usize = 20;
+
+/// The best long runs, either from this browser or from a leaderboard
+/// server. Only runs with the same rules, bet sizing and hand count are ranked
+/// together.
+pub struct HighScores {
+    link: ComponentLink<Self>,
+    scores: Scores,
+    fetch: FetchService,
+    task: Option<FetchTask>,
+    /// Scores from the server, once they've arrived
+    remote: Option<Vec<ScoreEntry>>,
+    /// Whether the server's scores are shown rather than this browser's
+    use_server: bool,
+    /// Which set of rules, bet sizing and hand count is shown, most recently
+    /// played first
+    group: usize,
+    message: String,
+}
+
+pub enum Msg {
+    SetPlayer(String),
+    SetServer(String),
+    ToggleSource,
+    NextGroup,
+    Fetched(Result<Vec<ScoreEntry>, String>),
+}
+
+impl Component for HighScores {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        HighScores {
+            link,
+            scores: Scores::open(),
+            fetch: FetchService::new(),
+            task: None,
+            remote: None,
+            use_server: false,
+            group: 0,
+            message: String::new(),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::SetPlayer(player) => {
+                self.scores.set_player(player);
+                return false;
+            }
+            Msg::SetServer(server) => {
+                self.scores.set_server(server);
+                self.remote = None;
+                return false;
+            }
+            Msg::ToggleSource => {
+                self.use_server = !self.use_server;
+                self.group = 0;
+                self.message = String::new();
+                if self.use_server && self.remote.is_none() {
+                    let server = match self.scores.server() {
+                        Some(server) => server.to_string(),
+                        None => {
+                            self.use_server = false;
+                            self.message = "Set a server first.".to_string();
+                            return true;
+                        }
+                    };
+                    self.message = format!("Fetching scores from {}...", server);
+                    self.task = leaderboard::scores(
+                        &mut self.fetch,
+                        &server,
+                        self.link.callback(Msg::Fetched),
+                    );
+                }
+            }
+            Msg::NextGroup => {
+                self.group = (self.group + 1) % self.groups().len().max(1);
+            }
+            Msg::Fetched(result) => {
+                self.task = None;
+                match result {
+                    Ok(entries) => {
+                        self.message = String::new();
+                        self.remote = Some(entries);
+                    }
+                    Err(error) => {
+                        self.use_server = false;
+                        self.message = format!("Couldn't reach the server: {}", error);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let groups = self.groups();
+        let like = groups.get(self.group).cloned();
+        let view_entry = |(i, entry): (usize, &&ScoreEntry)| {
+            html! {
+                <tr>
+                    <td>{ (i + 1).to_string() }</td>
+                    <td>{ if entry.player.is_empty() { "Anonymous" } else { entry.player.as_str() } }</td>
+                    <td>{ format!("{:+}", entry.score) }</td>
+                    <td>{ entry.bet_sizing.to_string() }</td>
+                    <td>{ entry.date.get(..10).unwrap_or(&entry.date) }</td>
+                    <td>{ entry.seed.to_string() }</td>
+                </tr>
+            }
+        };
+        html! {
+            <div class=("container-feature", "container", "container-trainer"),>
+                <div class=("container", "trainer-question"),>
+                    <div class=("chooser-settings", "container"),>
+                        <span>{ "Your name" }</span>
+                        <input class="settings-input", type="text", value=self.scores.board.player.clone(), oninput=self.link.callback(|e: InputData| Msg::SetPlayer(e.value)),/>
+                        <span>{ "Server" }</span>
+                        <input class="settings-input", type="text", placeholder="http://localhost:8080", value=self.scores.board.server.clone(), oninput=self.link.callback(|e: InputData| Msg::SetServer(e.value)),/>
+                        <span>{ "Showing" }</span>
+                        <button class=("btn-3d", "cyan", "active"), onclick=self.link.callback(|_| Msg::ToggleSource),>{ if self.use_server { "Server" } else { "This browser" } }</button>
+                        <span>{ "Rules" }</span>
+                        <button class=("btn-3d", "yellow", if groups.len() > 1 { "active" } else { "inactive" }), onclick=self.link.callback(|_| Msg::NextGroup),>{
+                            match &like {
+                                Some(like) => format!("{}, {} bets, {} hands", like.rules, like.bet_sizing, like.rounds),
+                                None => "No scores yet".to_string(),
+                            }
+                        }</button>
+                    </div>
+                    <table class="scores-table",>
+                        <tr>
+                            <th>{ "#" }</th>
+                            <th>{ "Player" }</th>
+                            <th>{ "Score" }</th>
+                            <th>{ "Bets" }</th>
+                            <th>{ "Date" }</th>
+                            <th>{ "Seed" }</th>
+                        </tr>
+                        {
+                            match &like {
+                                Some(like) => html! {
+                                    <>{ for blackjack::rank(self.entries(), like).iter().take(ENTRIES_SHOWN).enumerate().map(view_entry) }</>
+                                },
+                                None => html! {<tr />},
+                            }
+                        }
+                    </table>
+                    <div class="trainer-feedback",>{ &self.message }</div>
+                </div>
+                <Messages: messages=help(),/>
+            </div>
+        }
+    }
+}
+
+impl HighScores {
+    fn entries(&self) -> &[ScoreEntry] {
+        match &self.remote {
+            Some(remote) if self.use_server => remote,
+            _ => &self.scores.board.entries,
+        }
+    }
+
+    /// One entry for each set of rules, bet sizing and hand count, most recent
+    /// first.
+    fn groups(&self) -> Vec<ScoreEntry> {
+        let mut groups: Vec<ScoreEntry> = Vec::new();
+        for entry in self.entries().iter().rev() {
+            if !groups.iter().any(|group| group.comparable(entry)) {
+                groups.push(entry.clone());
+            }
+        }
+        groups
+    }
+}
+
+fn help() -> Vec<String> {
+    vec![
+        "--- HIGH SCORES ---".to_string(),
+        "Every long run you finish is scored here, with the chart, rules and seed it was played with.".to_string(),
+        "Runs are only ranked against others with the same rules, bet sizing and number of hands. The yellow button switches between them.".to_string(),
+        "Set a server to send your scores to a shared leaderboard too. It replays each run to check the score before it goes up.".to_string(),
+    ]
+}
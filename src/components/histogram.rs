@@ -0,0 +1,75 @@
+use yew::prelude::*;
+
+const WIDTH: f64 = 400.;
+const HEIGHT: f64 = 200.;
+const PADDING: f64 = 10.;
+const LABEL_HEIGHT: f64 = 15.;
+
+pub struct Histogram {
+    title: String,
+    bins: Vec<(String, usize)>,
+}
+
+pub enum Msg {}
+
+#[derive(PartialEq, Clone, Properties)]
+pub struct Props {
+    #[props(required)]
+    pub title: String,
+    /// (label, count) for each bar, left to right
+    #[props(required)]
+    pub bins: Vec<(String, usize)>,
+}
+
+impl Component for Histogram {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Histogram {
+            title: props.title,
+            bins: props.bins,
+        }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.title = props.title;
+        self.bins = props.bins;
+        true
+    }
+
+    fn view(&self) -> Html {
+        if self.bins.is_empty() {
+            return html! { <span /> };
+        }
+        let tallest = self.bins.iter().map(|&(_, n)| n).max().unwrap_or(0).max(1) as f64;
+        let total = self.bins.iter().map(|&(_, n)| n).sum::<usize>().max(1) as f64;
+        let bar_width = (WIDTH - 2. * PADDING) / self.bins.len() as f64;
+        let bar_space = HEIGHT - 2. * PADDING - LABEL_HEIGHT;
+        let gap = bar_width.min(2.) / 2.;
+
+        html! {
+            <div class=("container", "chart"),>
+                <span class="chart-title",>{ &self.title }</span>
+                <svg class="chart-svg", viewBox=format!("0 0 {} {}", WIDTH, HEIGHT),>
+                    { for self.bins.iter().enumerate().map(|(i, (label, count))| {
+                        let x = PADDING + i as f64 * bar_width;
+                        let height = *count as f64 / tallest * bar_space;
+                        html! {
+                            <g>
+                                <rect class="histogram-bar", x=(x + gap), y=(PADDING + bar_space - height), width=(bar_width - 2. * gap), height=height,>
+                                    <title>{ format!("{}: {} ({:.2}%)", label, count, *count as f64 / total * 100.) }</title>
+                                </rect>
+                                <text class="chart-label", x=(x + bar_width / 2.), y=(HEIGHT - PADDING), text-anchor="middle",>{ label }</text>
+                            </g>
+                        }
+                    })}
+                </svg>
+            </div>
+        }
+    }
+}
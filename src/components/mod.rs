@@ -1,5 +1,11 @@
 pub mod controls;
 // pub mod dashboard;
 pub mod action_button;
+pub mod chart;
 pub mod chooser;
+pub mod count_drill;
+pub mod high_scores;
+pub mod histogram;
 pub mod messages;
+pub mod play;
+pub mod trainer;
@@ -0,0 +1,185 @@
+use crate::blackjack::{self, Action, HandView};
+use yew::prelude::*;
+
+const BANKROLL: usize = 1_000;
+const CHIPS: [(usize, &str); 4] = [(1, "white"), (5, "red"), (25, "green"), (100, "black")];
+
+/// A table to play hands on yourself, with the engine dealing.
+pub struct Play {
+    link: ComponentLink<Self>,
+    table: blackjack::Table,
+    bet: usize,
+    message: String,
+}
+
+pub enum Msg {
+    AddChip(usize),
+    ClearBet,
+    Deal,
+    Act(Action),
+}
+
+impl Component for Play {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Play {
+            link,
+            table: blackjack::Table::new(blackjack::Rules::default(), BANKROLL),
+            bet: CHIPS[1].0,
+            message: "Click the chips to place your bet, then deal.".to_string(),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::AddChip(chip) => {
+                self.bet += chip;
+                self.message = String::new();
+            }
+            Msg::ClearBet => self.bet = 0,
+            Msg::Deal => match self.table.deal(self.bet) {
+                Ok(_) => {
+                    self.message = String::new();
+                    self.settled();
+                }
+                Err(e) => self.message = e.to_string(),
+            },
+            Msg::Act(action) => {
+                if self.table.act(action).is_err() {
+                    return false;
+                }
+                self.message = String::new();
+                self.settled();
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let active = self.table.active_hand();
+        html! {
+            <div class=("container-feature", "container", "container-play"),>
+                <div class="play-status",>
+                    { format!("Bankroll: ${}", self.table.balance()) }
+                </div>
+                {
+                    match self.table.dealer() {
+                        Some(dealer) => view_hand("Dealer", &dealer, false),
+                        None => html! {<div class="play-hand",/>},
+                    }
+                }
+                <div class="play-hands",>
+                    { for self.table.hands().iter().enumerate().map(|(i, hand)| view_hand(&format!("Hand {}", i + 1), hand, active == Some(i))) }
+                </div>
+                <div class="play-message",>{ &self.message }</div>
+                <div class="play-buttons",>
+                    {
+                        if self.table.in_round() {
+                            self.view_actions()
+                        } else {
+                            self.view_chips()
+                        }
+                    }
+                </div>
+            </div>
+        }
+    }
+}
+
+impl Play {
+    /// Puts the results of the round in the message once it's over.
+    fn settled(&mut self) {
+        if self.table.in_round() {
+            return;
+        }
+        let results: Vec<String> = self
+            .table
+            .hands()
+            .iter()
+            .filter_map(|hand| hand.settlement)
+            .map(|(settlement, net)| format!("{} ({:+})", settlement, net))
+            .collect();
+        let mut clauses = Vec::new();
+        if !results.is_empty() {
+            clauses.push(results.join(", "));
+        }
+        if self.table.balance() == 0 {
+            clauses.push("You're broke!".to_string());
+        }
+        if !clauses.is_empty() {
+            self.message = clauses.join(". ");
+        }
+    }
+
+    fn view_actions(&self) -> Html {
+        let actions = self.table.actions();
+        let view_action = |&action: &Action| {
+            let color = match action {
+                Action::Hit => "green",
+                Action::Stand => "red",
+                Action::Double => "yellow",
+                Action::Split => "blue",
+                Action::Surrender => "purple",
+            };
+            if actions.contains(&action) {
+                html! {
+                    <button class=("btn-3d", color, "active"), onclick=self.link.callback(move |_| Msg::Act(action)),>{ action.to_string() }</button>
+                }
+            } else {
+                html! {
+                    <button class=("btn-3d", color, "inactive"),>{ action.to_string() }</button>
+                }
+            }
+        };
+        html! {
+            <>{ for Action::ALL.iter().map(view_action) }</>
+        }
+    }
+
+    fn view_chips(&self) -> Html {
+        let view_chip = |&(chip, color): &(usize, &'static str)| {
+            html! {
+                <button class=("chip", color), onclick=self.link.callback(move |_| Msg::AddChip(chip)),>{ format!("${}", chip) }</button>
+            }
+        };
+        html! {
+            <>
+            { for CHIPS.iter().map(view_chip) }
+            <span class="play-bet",>{ format!("Bet: ${}", self.bet) }</span>
+            <button class=("btn-3d", "blue", "active"), onclick=self.link.callback(|_| Msg::ClearBet),>{ " Clear " }</button>
+            <button class=("btn-3d", "purple", "active"), onclick=self.link.callback(|_| Msg::Deal),>{ " Deal " }</button>
+            </>
+        }
+    }
+}
+
+/// A row of cards with the hand's total, bet and result next to them.
+fn view_hand(label: &str, hand: &HandView, active: bool) -> Html {
+    let view_card = |card: &String| {
+        if card == "?" {
+            html! { <div class=("playing-card", "face-down"),/> }
+        } else {
+            html! { <div class="playing-card",>{ card }</div> }
+        }
+    };
+    let mut summary = format!("{}{}", if hand.soft { "soft " } else { "" }, hand.total);
+    if hand.bet > 0 {
+        summary.push_str(&format!(", bet ${}", hand.bet));
+    }
+    if let Some((settlement, net)) = hand.settlement {
+        summary.push_str(&format!(" - {} ({:+})", settlement, net));
+    }
+    html! {
+        <div class=("play-hand", if active { "play-hand-active" } else { "" }),>
+            <span class="play-label",>{ label }</span>
+            { for hand.cards.iter().map(view_card) }
+            <span class="play-summary",>{ summary }</span>
+        </div>
+    }
+}
@@ -0,0 +1,179 @@
+use crate::blackjack;
+use crate::components::messages::Messages;
+use yew::prelude::*;
+
+const WEAKEST_SHOWN: usize = 8;
+
+/// Drills the decisions in the Chooser's charts, checking each answer against
+/// either the chart the user built or basic strategy.
+pub struct Trainer {
+    link: ComponentLink<Self>,
+    trainer: blackjack::Trainer,
+    chart: Option<blackjack::Strategy>,
+    optimal: blackjack::Strategy,
+    /// Whether answers are checked against `chart` rather than `optimal`
+    use_chart: bool,
+    feedback: Option<(bool, String)>,
+}
+
+pub enum Msg {
+    Answer(&'static str),
+    ToggleReference,
+}
+
+#[derive(Properties, Clone, Debug)]
+pub struct Props {
+    /// The chart from the Chooser, if the trainer was started from there
+    pub chart: Option<blackjack::Strategy>,
+    /// The rules the chart was built for, which basic strategy follows too
+    pub rules: blackjack::Rules,
+}
+
+impl Component for Trainer {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Trainer {
+            link,
+            trainer: blackjack::Trainer::new(),
+            use_chart: props.chart.is_some(),
+            chart: props.chart,
+            optimal: blackjack::Strategy::optimal(&props.rules),
+            feedback: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Answer(code) => {
+                let cell = self.trainer.cell();
+                let reference = match &self.chart {
+                    Some(chart) if self.use_chart => chart,
+                    _ => &self.optimal,
+                };
+                let answer = cell.code(reference);
+                let right = self.trainer.answer(code, reference);
+                self.feedback = Some(if right {
+                    (true, format!("Correct! {}: {}", cell, code_name(answer)))
+                } else {
+                    (
+                        false,
+                        format!(
+                            "Not quite. {}: {} says {}",
+                            cell,
+                            self.reference_name(),
+                            code_name(answer)
+                        ),
+                    )
+                });
+            }
+            Msg::ToggleReference => {
+                if self.chart.is_none() {
+                    return false;
+                }
+                self.use_chart = !self.use_chart;
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.use_chart = props.chart.is_some();
+        self.chart = props.chart;
+        self.optimal = blackjack::Strategy::optimal(&props.rules);
+        true
+    }
+
+    fn view(&self) -> Html {
+        let cell = self.trainer.cell();
+        let answers: &[&'static str] = match cell.chart {
+            blackjack::Chart::Split => &["y", "n"],
+            _ => &["h", "s", "d"],
+        };
+        let view_answer = |&code: &&'static str| {
+            let color = match code {
+                "h" | "y" => "green",
+                "s" | "n" => "red",
+                _ => "yellow",
+            };
+            html! {
+                <button class=("btn-3d", color, "active"), onclick=self.link.callback(move |_| Msg::Answer(code)),>{ code_name(code) }</button>
+            }
+        };
+        html! {
+            <div class=("container-feature", "container", "container-trainer"),>
+                <div class=("container", "trainer-question"),>
+                    <button class=("btn-3d", "cyan", if self.chart.is_some() { "active" } else { "inactive" }), onclick=self.link.callback(|_| Msg::ToggleReference),>{
+                        format!("Checking against: {}", self.reference_name())
+                    }</button>
+                    <div class="play-hand",>
+                        <span class="play-label",>{ "Dealer" }</span>
+                        <div class="playing-card",>{ cell.upcard() }</div>
+                        <div class=("playing-card", "face-down"),/>
+                    </div>
+                    <div class="play-hand",>
+                        <span class="play-label",>{ "You" }</span>
+                        { for self.trainer.cards().iter().map(|card| html! { <div class="playing-card",>{ card }</div> }) }
+                    </div>
+                    <div>{ for answers.iter().map(view_answer) }</div>
+                    {
+                        match &self.feedback {
+                            Some((right, text)) => html! {
+                                <div class=("trainer-feedback", if *right { "trainer-right" } else { "trainer-wrong" }),>{ text }</div>
+                            },
+                            None => html! {<div class="trainer-feedback",/>},
+                        }
+                    }
+                </div>
+                <Messages: messages=self.stats(),/>
+            </div>
+        }
+    }
+}
+
+impl Trainer {
+    fn reference_name(&self) -> &'static str {
+        match &self.chart {
+            Some(_) if self.use_chart => "Your chart",
+            _ => "Basic strategy",
+        }
+    }
+
+    /// Overall accuracy, then the cells that need the most work.
+    fn stats(&self) -> Vec<String> {
+        let (right, wrong) = self.trainer.accuracy();
+        let mut stats = vec![
+            "--- TRAINER ---".to_string(),
+            "Pick what you'd do with your hand against the dealer's card.".to_string(),
+            "Hands you get wrong will come up more often.".to_string(),
+        ];
+        if right + wrong > 0 {
+            stats.push(format!(
+                "Accuracy: {}/{} ({:.0}%)",
+                right,
+                right + wrong,
+                right as f64 / (right + wrong) as f64 * 100.
+            ));
+        }
+        let weakest = self.trainer.weakest(WEAKEST_SHOWN);
+        if !weakest.is_empty() {
+            stats.push("Needs work:".to_string());
+            stats.extend(weakest.iter().map(|(cell, (right, wrong))| {
+                format!("{} - {}/{} right", cell, right, right + wrong)
+            }));
+        }
+        stats
+    }
+}
+
+fn code_name(code: &str) -> &'static str {
+    match code {
+        "h" => "Hit",
+        "s" => "Stand",
+        "d" => "Double",
+        "y" => "Split",
+        "n" => "Don't split",
+        _ => "?",
+    }
+}
@@ -0,0 +1,51 @@
+use crate::blackjack::ScoreEntry;
+use yew::callback::Callback;
+use yew::format::{Json, Nothing, Text};
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+
+/// Sends `entry` to the leaderboard server at `server`. The callback gets the
+/// server's reason if it turns the score down.
+pub fn submit(
+    fetch: &mut FetchService,
+    server: &str,
+    entry: &ScoreEntry,
+    callback: Callback<Result<(), String>>,
+) -> Option<FetchTask> {
+    let request = Request::post(scores_url(server))
+        .header("Content-Type", "application/json")
+        .body(Json(entry))
+        .ok()?;
+    let handler = move |response: Response<Text>| {
+        let (meta, body) = response.into_parts();
+        callback.emit(match body {
+            Ok(_) if meta.status.is_success() => Ok(()),
+            Ok(body) => Err(body),
+            Err(error) => Err(error.to_string()),
+        })
+    };
+    fetch.fetch(request, handler.into()).ok()
+}
+
+/// Fetches every score the server at `server` has.
+pub fn scores(
+    fetch: &mut FetchService,
+    server: &str,
+    callback: Callback<Result<Vec<ScoreEntry>, String>>,
+) -> Option<FetchTask> {
+    let request = Request::get(scores_url(server)).body(Nothing).ok()?;
+    let handler = move |response: Response<Text>| {
+        let (meta, body) = response.into_parts();
+        callback.emit(match body {
+            Ok(body) if meta.status.is_success() => {
+                serde_json::from_str(&body).map_err(|error| error.to_string())
+            }
+            Ok(body) => Err(body),
+            Err(error) => Err(error.to_string()),
+        })
+    };
+    fetch.fetch(request, handler.into()).ok()
+}
+
+fn scores_url(server: &str) -> String {
+    format!("{}/scores", server.trim_end_matches('/'))
+}
@@ -0,0 +1,169 @@
+use crate::blackjack;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use stdweb::web::Date;
+use yew::agent::{Agent, AgentLink, HandlerId, Public};
+use yew::services::{timeout::TimeoutTask, TimeoutService};
+
+const SHOES_PER_STEP: usize = 500;
+const SESSIONS_PER_STEP: usize = 10;
+const COMPARISON_ROUNDS_PER_STEP: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Request {
+    Run(
+        blackjack::Strategy,
+        blackjack::Rules,
+        blackjack::BetSizing,
+        blackjack::Stopping,
+    ),
+    Sessions(
+        blackjack::Strategy,
+        blackjack::Rules,
+        blackjack::BetSizing,
+        blackjack::SessionConfig,
+    ),
+    /// Named strategies to play head to head for this many rounds each
+    Compare(
+        Vec<(String, blackjack::Strategy)>,
+        blackjack::Rules,
+        blackjack::BetSizing,
+        usize,
+    ),
+    Cancel,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Response {
+    /// Fraction of the job done so far, and the results up to this point
+    Progress(f64, blackjack::Report),
+    Done(blackjack::Report),
+}
+
+enum Job {
+    Run(blackjack::Simulation),
+    Sessions(blackjack::SessionSimulation),
+    Compare(blackjack::Comparison),
+}
+
+pub enum Msg {
+    Step,
+}
+
+/// Runs simulations in a Web Worker, a few shoes or sessions at a time, so
+/// the page stays responsive, sees the results so far and can stop part way
+/// through.
+pub struct Simulator {
+    link: AgentLink<Self>,
+    timeout: TimeoutService,
+    task: Option<TimeoutTask>,
+    job: Option<(HandlerId, Job)>,
+    /// When the current job started, in milliseconds since the epoch
+    started: f64,
+}
+
+impl Agent for Simulator {
+    type Reach = Public;
+    type Message = Msg;
+    type Input = Request;
+    type Output = Response;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        Simulator {
+            link,
+            timeout: TimeoutService::new(),
+            task: None,
+            job: None,
+            started: 0.,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) {
+        match msg {
+            Msg::Step => {
+                let (who, done, progress, report) = match &mut self.job {
+                    Some((who, Job::Run(simulation))) => {
+                        simulation.step(SHOES_PER_STEP);
+                        let mut progress = simulation.progress();
+                        if let blackjack::Stopping::Precision { seconds, .. } =
+                            simulation.stopping()
+                        {
+                            let elapsed = (Date::now() - self.started) / 1000.;
+                            if elapsed >= seconds as f64 {
+                                simulation.time_out();
+                            }
+                            progress = progress.max(elapsed / seconds.max(1) as f64);
+                        }
+                        (
+                            *who,
+                            simulation.is_done(),
+                            progress,
+                            blackjack::Report::Run(simulation.outcome()),
+                        )
+                    }
+                    Some((who, Job::Sessions(simulation))) => (
+                        *who,
+                        simulation.step(SESSIONS_PER_STEP),
+                        simulation.progress(),
+                        blackjack::Report::Sessions(simulation.report()),
+                    ),
+                    Some((who, Job::Compare(comparison))) => (
+                        *who,
+                        comparison.step(COMPARISON_ROUNDS_PER_STEP),
+                        comparison.progress(),
+                        blackjack::Report::Comparison(comparison.report()),
+                    ),
+                    None => return,
+                };
+                if done {
+                    self.job = None;
+                    self.link.respond(who, Response::Done(report));
+                } else {
+                    self.link.respond(who, Response::Progress(progress, report));
+                    self.schedule();
+                }
+            }
+        }
+    }
+
+    fn handle_input(&mut self, msg: Self::Input, who: HandlerId) {
+        let job = match msg {
+            Request::Run(strategy, rules, bet_sizing, stopping) => Job::Run(
+                blackjack::Simulation::new(strategy, rules, bet_sizing, stopping),
+            ),
+            Request::Sessions(strategy, rules, bet_sizing, config) => {
+                Job::Sessions(blackjack::SessionSimulation::new(
+                    strategy,
+                    rules,
+                    bet_sizing,
+                    config,
+                    rand::random(),
+                ))
+            }
+            Request::Compare(entrants, rules, bet_sizing, rounds) => Job::Compare(
+                blackjack::Comparison::new(entrants, rules, bet_sizing, rounds, rand::random()),
+            ),
+            Request::Cancel => {
+                self.job = None;
+                self.task = None;
+                return;
+            }
+        };
+        self.job = Some((who, job));
+        self.started = Date::now();
+        self.schedule();
+    }
+
+    fn name_of_resource() -> &'static str {
+        "simulator.js"
+    }
+}
+
+impl Simulator {
+    /// Queues the next step behind any waiting requests, so a `Cancel` can
+    /// get through.
+    fn schedule(&mut self) {
+        let callback = self.link.callback(|_| Msg::Step);
+        self.task = Some(self.timeout.spawn(Duration::from_millis(0), callback));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::blackjack;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use yew::format::Json;
+use yew::services::storage::{Area, StorageService};
+
+const KEY: &str = "blackjack-online.strategies";
+const SCORES_KEY: &str = "blackjack-online.scores";
+
+/// Everything the Chooser keeps between visits. Charts are kept with the
+/// rules they were built for; ones saved before that load with the default
+/// rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Library {
+    /// The chart being edited, autosaved on every change
+    pub working: blackjack::ChartFile,
+    /// What the working chart was last saved as or loaded from
+    pub name: String,
+    pub saved: BTreeMap<String, blackjack::ChartFile>,
+    /// Whether the chart is drawn in colors that don't rely on telling red from green
+    #[serde(default)]
+    pub colorblind: bool,
+}
+
+impl Default for Library {
+    fn default() -> Self {
+        Library {
+            working: blackjack::ChartFile {
+                rules: blackjack::Rules::default(),
+                strategy: blackjack::Strategy::basic(),
+            },
+            name: String::new(),
+            saved: BTreeMap::new(),
+            colorblind: false,
+        }
+    }
+}
+
+/// Named strategies in the browser's local storage. If storage is turned off
+/// everything still works, it just doesn't outlive the page.
+pub struct Strategies {
+    storage: Option<StorageService>,
+    pub library: Library,
+}
+
+impl Strategies {
+    pub fn open() -> Self {
+        let storage = StorageService::new(Area::Local).ok();
+        let library = restore(&storage, KEY);
+        Strategies { storage, library }
+    }
+
+    pub fn autosave(&mut self, working: blackjack::ChartFile) {
+        self.library.working = working;
+        self.persist();
+    }
+
+    /// Saves the working chart as `name`, replacing any chart already saved
+    /// under it.
+    pub fn save(&mut self, name: &str) {
+        self.library
+            .saved
+            .insert(name.to_string(), self.library.working.clone());
+        self.library.name = name.to_string();
+        self.persist();
+    }
+
+    /// Makes `name` the working chart.
+    pub fn load(&mut self, name: &str) -> Option<blackjack::ChartFile> {
+        let chart = self.library.saved.get(name)?.clone();
+        self.library.working = chart.clone();
+        self.library.name = name.to_string();
+        self.persist();
+        Some(chart)
+    }
+
+    /// Saves a copy of `name` under the first free "<name> copy" name.
+    pub fn duplicate(&mut self, name: &str) {
+        let chart = match self.library.saved.get(name) {
+            Some(chart) => chart.clone(),
+            None => return,
+        };
+        let mut copy = format!("{} copy", name);
+        let mut n = 2;
+        while self.library.saved.contains_key(&copy) {
+            copy = format!("{} copy {}", name, n);
+            n += 1;
+        }
+        self.library.saved.insert(copy, chart);
+        self.persist();
+    }
+
+    pub fn delete(&mut self, name: &str) {
+        self.library.saved.remove(name);
+        self.persist();
+    }
+
+    pub fn set_colorblind(&mut self, colorblind: bool) {
+        self.library.colorblind = colorblind;
+        self.persist();
+    }
+
+    fn persist(&mut self) {
+        if let Some(storage) = &mut self.storage {
+            storage.store(KEY, Json(&self.library));
+        }
+    }
+}
+
+/// The High Scores board as it's kept between visits.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Board {
+    /// Name put on new scores
+    pub player: String,
+    /// Leaderboard server that new scores are also sent to, if any
+    pub server: String,
+    pub entries: Vec<blackjack::ScoreEntry>,
+}
+
+/// Scores from long runs in the browser's local storage.
+pub struct Scores {
+    storage: Option<StorageService>,
+    pub board: Board,
+}
+
+impl Scores {
+    pub fn open() -> Self {
+        let storage = StorageService::new(Area::Local).ok();
+        let board = restore(&storage, SCORES_KEY);
+        Scores { storage, board }
+    }
+
+    pub fn record(&mut self, entry: blackjack::ScoreEntry) {
+        self.board.entries.push(entry);
+        self.persist();
+    }
+
+    pub fn set_player(&mut self, player: String) {
+        self.board.player = player;
+        self.persist();
+    }
+
+    pub fn set_server(&mut self, server: String) {
+        self.board.server = server;
+        self.persist();
+    }
+
+    /// The server to send scores to, if one is set
+    pub fn server(&self) -> Option<&str> {
+        match self.board.server.trim() {
+            "" => None,
+            server => Some(server),
+        }
+    }
+
+    fn persist(&mut self) {
+        if let Some(storage) = &mut self.storage {
+            storage.store(SCORES_KEY, Json(&self.board));
+        }
+    }
+}
+
+/// What's stored under `key`, or the default if there's nothing readable.
+fn restore<T: Default + DeserializeOwned>(storage: &Option<StorageService>, key: &str) -> T {
+    match storage {
+        Some(storage) => {
+            if let Json(Ok(value)) = storage.restore(key) {
+                value
+            } else {
+                T::default()
+            }
+        }
+        None => T::default(),
+    }
+}